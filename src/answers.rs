@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::solution::Answers;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum AnswersError {
+    #[error("line {0}: expected `[dayNN]` or `part1`/`part2 = \"<answer>\"`")]
+    Line(usize),
+    #[error("line {0}: answer outside a `[dayNN]` section")]
+    NoDay(usize),
+    #[error("cannot access answers file {0}")]
+    File(String),
+}
+
+/// Expected answers per day, stored in a small subset of TOML:
+///
+/// ```toml
+/// [day01]
+/// part1 = "3412094"
+/// part2 = "5115267"
+/// ```
+///
+/// Answers are basic strings, so multi-line ones are written with `\n`, and
+/// `#` starts a comment outside them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnswerFile {
+    days: BTreeMap<usize, Answers>,
+}
+
+fn escape(answer: &str) -> String {
+    let mut out = String::with_capacity(answer.len());
+    for c in answer.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(value: &str) -> Option<String> {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '\\' => out.push('\\'),
+                '"' => out.push('"'),
+                'n' => out.push('\n'),
+                _ => return None,
+            },
+            '"' => return None,
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+impl fmt::Display for AnswerFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (day, answers)) in self.days.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[day{day:02}]")?;
+            for (key, answer) in [("part1", &answers.part1), ("part2", &answers.part2)] {
+                if let Some(answer) = answer {
+                    writeln!(f, "{key} = \"{}\"", escape(answer))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `line` without a trailing `# comment`, ignoring `#` inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+impl FromStr for AnswerFile {
+    type Err = AnswersError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut days = BTreeMap::new();
+        let mut day = None;
+        for (line, content) in s.lines().enumerate() {
+            let content = strip_comment(content).trim();
+            if content.is_empty() {
+                continue;
+            }
+            let line = line + 1;
+            if let Some(section) = content.strip_prefix('[') {
+                let number = section
+                    .strip_suffix(']')
+                    .and_then(|section| section.trim().strip_prefix("day"))
+                    .and_then(|number| number.parse().ok())
+                    .ok_or(AnswersError::Line(line))?;
+                days.entry(number).or_insert_with(Answers::default);
+                day = Some(number);
+                continue;
+            }
+
+            let (key, value) = content.split_once('=').ok_or(AnswersError::Line(line))?;
+            let value = value.trim();
+            let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                Some(quoted) => unescape(quoted),
+                None => value.parse::<i64>().ok().map(|n| n.to_string()),
+            }
+            .ok_or(AnswersError::Line(line))?;
+            let answers = days
+                .get_mut(&day.ok_or(AnswersError::NoDay(line))?)
+                .unwrap();
+            match key.trim() {
+                "part1" => answers.part1 = Some(value),
+                "part2" => answers.part2 = Some(value),
+                _ => return Err(AnswersError::Line(line)),
+            }
+        }
+        Ok(Self { days })
+    }
+}
+
+impl AnswerFile {
+    /// Reads an answers file, treating a missing one as empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AnswersError> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => content.parse(),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(_) => Err(AnswersError::File(path.display().to_string())),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), AnswersError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_string())
+            .map_err(|_| AnswersError::File(path.display().to_string()))
+    }
+
+    pub fn get(&self, day: usize) -> Option<&Answers> {
+        self.days.get(&day)
+    }
+
+    /// Records the answers not yet known for `day`. Returns whether any were
+    /// added.
+    pub fn fill(&mut self, day: usize, answers: &Answers) -> bool {
+        let known = self.days.entry(day).or_default();
+        let mut added = false;
+        for (known, answer) in [
+            (&mut known.part1, &answers.part1),
+            (&mut known.part2, &answers.part2),
+        ] {
+            if known.is_none() && answer.is_some() {
+                known.clone_from(answer);
+                added = true;
+            }
+        }
+        added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"# answers for the default inputs
+[day01]  # first puzzle
+part1 = "3412094" # checked by hand
+part2 = 5115267#no space
+
+[day11]
+part2 = "\n#..#\n\"x\"\\" # has "quotes"
+"#;
+
+    #[test]
+    fn test_round_trip() {
+        let mut file: AnswerFile = FILE.parse().unwrap();
+        assert_eq!(
+            file.get(1),
+            Some(&Answers {
+                part1: Some("3412094".into()),
+                part2: Some("5115267".into()),
+            })
+        );
+        assert_eq!(
+            file.get(11).unwrap().part2.as_deref(),
+            Some("\n#..#\n\"x\"\\")
+        );
+        assert_eq!(file.get(2), None);
+        assert_eq!(file.to_string().parse(), Ok(file.clone()));
+
+        let answers = Answers {
+            part1: Some("1".into()),
+            part2: Some("2".into()),
+        };
+        assert!(file.fill(11, &answers));
+        assert!(!file.fill(11, &answers));
+        assert_eq!(file.get(11).unwrap().part1.as_deref(), Some("1"));
+        assert_eq!(
+            file.get(11).unwrap().part2.as_deref(),
+            Some("\n#..#\n\"x\"\\")
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!("[day1".parse::<AnswerFile>(), Err(AnswersError::Line(1)));
+        assert_eq!(
+            "part1 = \"1\"".parse::<AnswerFile>(),
+            Err(AnswersError::NoDay(1))
+        );
+        assert_eq!(
+            "[day03]\npart3 = \"1\"".parse::<AnswerFile>(),
+            Err(AnswersError::Line(2))
+        );
+        assert_eq!(
+            "[day03]\npart1 = \"a\\tb\"".parse::<AnswerFile>(),
+            Err(AnswersError::Line(2))
+        );
+    }
+}
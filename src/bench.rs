@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::json::{Json, JsonError};
+use crate::solution::{Day, Setup, SolveError};
+
+pub const PHASES: [&str; 4] = ["parse", "part1", "part2", "total"];
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum BaselineError {
+    #[error("invalid baseline: {0}")]
+    Json(#[from] JsonError),
+    #[error("invalid baseline: missing or malformed {0}")]
+    Field(String),
+    #[error("cannot access baseline {0}")]
+    File(String),
+}
+
+/// Summary of repeated timings of one phase.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub mean: Duration,
+    pub median: Duration,
+    pub std_dev: Duration,
+    pub min: Duration,
+}
+
+impl Stats {
+    pub fn new(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let median = if n % 2 == 1 {
+            sorted[n / 2]
+        } else {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2
+        };
+
+        let secs: Vec<f64> = sorted.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / n as f64;
+        let variance = match n {
+            1 => 0.0,
+            _ => secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64,
+        };
+        Self {
+            mean: Duration::from_secs_f64(mean),
+            median,
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+            min: sorted[0],
+        }
+    }
+
+    fn to_json(self) -> Json {
+        let ns = |duration: Duration| Json::Number(duration.as_nanos() as f64);
+        Json::Object(vec![
+            ("mean_ns".into(), ns(self.mean)),
+            ("median_ns".into(), ns(self.median)),
+            ("std_dev_ns".into(), ns(self.std_dev)),
+            ("min_ns".into(), ns(self.min)),
+        ])
+    }
+
+    fn from_json(json: &Json) -> Option<Self> {
+        let ns = |key: &str| {
+            let ns = json.get(key)?.as_f64().filter(|ns| *ns >= 0.0)?;
+            Some(Duration::from_nanos(ns as u64))
+        };
+        Some(Self {
+            mean: ns("mean_ns")?,
+            median: ns("median_ns")?,
+            std_dev: ns("std_dev_ns")?,
+            min: ns("min_ns")?,
+        })
+    }
+}
+
+/// Statistics of each of `PHASES` for one day.
+pub type Phases = [Stats; 4];
+
+/// Solves `input` `warmup` times untimed, then `iterations` times timed.
+pub fn measure(
+    solution: &dyn Day,
+    input: &str,
+    warmup: usize,
+    iterations: usize,
+) -> Result<Phases, SolveError> {
+    for _ in 0..warmup {
+        solution.solve(input, None)?;
+    }
+    let mut samples = [(); 4].map(|_| Vec::with_capacity(iterations));
+    for _ in 0..iterations {
+        let (_, timings) = solution.solve_timed(input, None, &Setup::default())?;
+        samples[0].push(timings.parse);
+        samples[1].push(timings.part1.unwrap_or_default());
+        samples[2].push(timings.part2.unwrap_or_default());
+        samples[3].push(timings.total());
+    }
+    Ok(samples.map(|samples| Stats::new(&samples)))
+}
+
+/// A phase whose median got slower than its baseline by more than the
+/// allowed fraction.
+#[derive(Debug, PartialEq)]
+pub struct Regression {
+    pub phase: &'static str,
+    pub baseline: Duration,
+    pub current: Duration,
+}
+
+impl Regression {
+    pub fn change(&self) -> f64 {
+        self.current.as_secs_f64() / self.baseline.as_secs_f64() - 1.0
+    }
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: median {:.1?} vs baseline {:.1?}, {:+.1}%",
+            self.phase,
+            self.current,
+            self.baseline,
+            100.0 * self.change()
+        )
+    }
+}
+
+/// Phases whose median exceeds the baseline median by more than `threshold`,
+/// a fraction such as 0.1 for 10%.
+pub fn compare(baseline: &Phases, current: &Phases, threshold: f64) -> Vec<Regression> {
+    PHASES
+        .iter()
+        .zip(baseline.iter().zip(current))
+        .filter(|(_, (baseline, current))| {
+            current.median.as_secs_f64() > baseline.median.as_secs_f64() * (1.0 + threshold)
+        })
+        .map(|(&phase, (baseline, current))| Regression {
+            phase,
+            baseline: baseline.median,
+            current: current.median,
+        })
+        .collect()
+}
+
+/// Saved benchmark results per day, stored as JSON:
+///
+/// ```json
+/// {"days": {"12": {"parse": {"mean_ns": 1200, "median_ns": 1100, ...}, ...}}}
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Baseline {
+    days: BTreeMap<usize, Phases>,
+}
+
+impl Baseline {
+    /// Reads a baseline, treating a missing file as empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BaselineError> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => Self::from_json(&content.parse()?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(_) => Err(BaselineError::File(path.display().to_string())),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BaselineError> {
+        let path = path.as_ref();
+        fs::write(path, format!("{:#}\n", self.to_json()))
+            .map_err(|_| BaselineError::File(path.display().to_string()))
+    }
+
+    pub fn get(&self, day: usize) -> Option<&Phases> {
+        self.days.get(&day)
+    }
+
+    pub fn insert(&mut self, day: usize, phases: Phases) {
+        self.days.insert(day, phases);
+    }
+
+    fn to_json(&self) -> Json {
+        let days = self
+            .days
+            .iter()
+            .map(|(day, phases)| {
+                let phases = PHASES
+                    .iter()
+                    .zip(phases)
+                    .map(|(phase, stats)| (phase.to_string(), stats.to_json()))
+                    .collect();
+                (day.to_string(), Json::Object(phases))
+            })
+            .collect();
+        Json::Object(vec![("days".into(), Json::Object(days))])
+    }
+
+    fn from_json(json: &Json) -> Result<Self, BaselineError> {
+        let field = |name: String| BaselineError::Field(name);
+        let days = json.get("days").ok_or_else(|| field("days".into()))?;
+        let mut baseline = Self::default();
+        for (day, phases) in days.entries() {
+            let number = day.parse().map_err(|_| field(format!("day `{day}`")))?;
+            let mut stats = [Stats::default(); 4];
+            for (stats, phase) in stats.iter_mut().zip(PHASES) {
+                *stats = phases
+                    .get(phase)
+                    .and_then(Stats::from_json)
+                    .ok_or_else(|| field(format!("{phase} of day {day}")))?;
+            }
+            baseline.insert(number, stats);
+        }
+        Ok(baseline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::new(&[ms(4), ms(1), ms(3), ms(2), ms(10)]);
+        assert_eq!(stats.min, ms(1));
+        assert_eq!(stats.median, ms(3));
+        assert_eq!(stats.mean, ms(4));
+        assert_eq!(stats.std_dev.as_micros(), 3535);
+        assert_eq!(
+            Stats::new(&[ms(1), ms(2)]).median,
+            Duration::from_micros(1500)
+        );
+    }
+
+    #[test]
+    fn test_baseline() {
+        let stats = |median| Stats {
+            mean: ms(median),
+            median: ms(median),
+            std_dev: Duration::from_nanos(1234),
+            min: ms(median - 1),
+        };
+        let mut baseline = Baseline::default();
+        baseline.insert(12, [stats(2), stats(10), stats(20), stats(32)]);
+        let json = format!("{:#}", baseline.to_json());
+        assert_eq!(
+            Baseline::from_json(&json.parse().unwrap()),
+            Ok(baseline.clone())
+        );
+        assert_eq!(
+            Baseline::from_json(&r#"{"days": {"3": {"parse": {}}}}"#.parse().unwrap()),
+            Err(BaselineError::Field("parse of day 3".into()))
+        );
+
+        let current = [stats(2), stats(12), stats(21), stats(35)];
+        let regressions = compare(baseline.get(12).unwrap(), &current, 0.1);
+        assert_eq!(
+            regressions,
+            [Regression {
+                phase: "part1",
+                baseline: ms(10),
+                current: ms(12),
+            }]
+        );
+        assert_eq!(
+            regressions[0].to_string(),
+            "part1: median 12.0ms vs baseline 10.0ms, +20.0%"
+        );
+    }
+}
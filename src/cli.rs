@@ -0,0 +1,657 @@
+use std::fmt;
+
+use advent_of_code_2019::input::Input;
+use advent_of_code_2019::intcode::patch::Patch;
+use advent_of_code_2019::solution::{Part, DAYS};
+
+pub const USAGE: &str = "\
+usage: advent-of-code-2019 <command> [options]
+
+commands:
+  run [DAYS...]       solve days and print their answers
+  list                list the implemented days
+  bench [DAYS...]     time how long days take to solve
+  check [DAYS...]     compare answers with the recorded ones
+  example [DAYS...]   run the puzzle examples against their answers
+  disasm <DAY|FILE|-> disassemble an Intcode program
+  diff <A> <B>        compare two Intcode transcripts
+  help [COMMAND]      show help for a command
+
+DAYS are numbers or ranges such as 3-7, and default to the whole calendar.
+A bare day number, as in `advent-of-code-2019 5`, is short for `run 5`.
+
+Inputs are read from input/dayNN.txt, or from the directory named by the
+AOC_INPUT_DIR environment variable, with one subdirectory per user.
+";
+
+const RUN: &str = "\
+usage: advent-of-code-2019 run [DAYS...] [options]
+
+Solves each day and prints its answers. Running several days ends with a
+summary table.
+
+options:
+  --part <1|2>          solve only one part
+  --jobs <N>            solve up to N days at once on separate threads; the
+                        output still comes in day order
+  --format <FORMAT>     text (default), or json or csv with one record per
+                        day and phase: user, day, title, part, answer,
+                        time_ns, status and error
+  --input <PATH|->      read the input of a single day from a file or stdin
+  --user <NAME>         read inputs from the NAME subdirectory
+  --all-users           run every user subdirectory in turn
+  --patch <ADDR=VALUE,...>
+                        overwrite cells of the selected days' Intcode
+                        programs, taking precedence over the days' own edits
+  --patch-file <PATH>   read the patch from a file
+  --record <PATH>       save the Intcode input/output transcript
+  --replay <PATH>       compare the run against a saved transcript
+";
+
+const LIST: &str = "\
+usage: advent-of-code-2019 list
+
+Lists the implemented days with their titles and example counts.
+";
+
+const BENCH: &str = "\
+usage: advent-of-code-2019 bench [DAYS...] [options]
+
+Solves each day repeatedly after a few untimed warm-up runs, and reports
+the mean, median, standard deviation and minimum time of each phase.
+
+options:
+  --iterations <N>      timed runs per day (default 10)
+  --warmup <N>          untimed runs per day first (default 3)
+  --save-baseline <PATH>
+                        store the results in a JSON baseline, keeping the
+                        other days it holds; not with --all-users
+  --baseline <PATH>     flag phases whose median got slower than the
+                        baseline's by more than the threshold
+  --threshold <PCT>     allowed slowdown in percent (default 10)
+  --input <PATH|->      read the input of a single day from a file or stdin
+  --user <NAME>         read inputs from the NAME subdirectory
+  --all-users           bench every user subdirectory in turn
+";
+
+const CHECK: &str = "\
+usage: advent-of-code-2019 check [DAYS...] [options]
+
+Runs each day's examples, solves its input and compares the answers with
+the ones recorded in answers.toml beside the inputs, or in NAME.answers.toml
+beside an --input file. Each day passes, fails, or is missing an input or a
+recorded answer.
+
+options:
+  --save                record the answers that are missing
+  --input <PATH|->      read the input of a single day from a file or stdin
+  --user <NAME>         read inputs from the NAME subdirectory
+  --all-users           check every user subdirectory in turn
+";
+
+const EXAMPLE: &str = "\
+usage: advent-of-code-2019 example [DAYS...]
+
+Solves the examples from the puzzle texts and compares the answers.
+";
+
+const DISASM: &str = "\
+usage: advent-of-code-2019 disasm <DAY|FILE|-> [options]
+
+Disassembles the Intcode program in a day's input, in a file, or on stdin.
+
+options:
+  --optimize            run the peephole optimizer first
+  --decompile           print structured pseudocode instead of a listing
+  --lint                report suspicious instructions instead of a listing
+";
+
+const DIFF: &str = "\
+usage: advent-of-code-2019 diff <A> <B>
+
+Compares two transcripts saved with `run --record` and prints the first
+event where they diverge.
+";
+
+const HELP: &str = "\
+usage: advent-of-code-2019 help [COMMAND]
+
+Shows the help for a command, or the list of commands.
+";
+
+/// A command line that could not be understood, with the help text of the
+/// command it was meant for.
+#[derive(Debug, PartialEq)]
+pub struct UsageError {
+    pub message: String,
+    pub help: &'static str,
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Run),
+    List,
+    Bench(Bench),
+    Check {
+        days: Vec<usize>,
+        input: Input,
+        save: bool,
+    },
+    Example {
+        days: Vec<usize>,
+    },
+    Disasm(Disasm),
+    Diff {
+        left: String,
+        right: String,
+    },
+    Help(&'static str),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub days: Vec<usize>,
+    pub input: Input,
+    pub part: Option<Part>,
+    pub jobs: usize,
+    pub format: Format,
+    pub patch: Option<Patch>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Bench {
+    pub days: Vec<usize>,
+    pub input: Input,
+    pub iterations: usize,
+    pub warmup: usize,
+    pub baseline: Option<String>,
+    pub save_baseline: Option<String>,
+    /// Allowed slowdown as a fraction.
+    pub threshold: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    Day(usize),
+    File(String),
+    Stdin,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum View {
+    Listing,
+    Decompile,
+    Lint,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Disasm {
+    pub source: Source,
+    pub optimize: bool,
+    pub view: View,
+}
+
+struct Args {
+    args: std::vec::IntoIter<String>,
+    help: &'static str,
+}
+
+impl Args {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, UsageError> {
+        Err(UsageError {
+            message: message.into(),
+            help: self.help,
+        })
+    }
+
+    fn value(&mut self, flag: &str) -> Result<String, UsageError> {
+        match self.args.next() {
+            Some(value) => Ok(value),
+            None => self.error(format!("{flag} requires a value")),
+        }
+    }
+
+    fn days(&self, arg: &str) -> Result<Vec<usize>, UsageError> {
+        let day = |s: &str| match s.parse() {
+            Ok(day) if DAYS.contains(&day) => Ok(day),
+            _ => self.error(format!(
+                "invalid day `{s}`: expected {}-{}",
+                DAYS.start(),
+                DAYS.end()
+            )),
+        };
+        match arg.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (day(first)?, day(last)?);
+                if first > last {
+                    return self.error(format!("empty day range `{arg}`"));
+                }
+                Ok((first..=last).collect())
+            }
+            None => Ok(vec![day(arg)?]),
+        }
+    }
+
+    /// Parses the flags choosing where puzzle inputs come from. Returns
+    /// whether `arg` was one of them.
+    fn input(&mut self, arg: &str, input: &mut Input) -> Result<bool, UsageError> {
+        let selected = match arg {
+            "--input" => match self.value(arg)?.as_str() {
+                "-" => Input::Stdin,
+                path => Input::File(path.into()),
+            },
+            "--user" => Input::User(self.value(arg)?),
+            "--all-users" => Input::AllUsers,
+            _ => return Ok(false),
+        };
+        if *input != Input::Dir {
+            return self.error("--input, --user and --all-users cannot be combined");
+        }
+        *input = selected;
+        Ok(true)
+    }
+
+    /// The days to run, defaulting to the whole calendar unless the input
+    /// is a single file.
+    fn days_for(&self, days: Vec<usize>, input: &Input) -> Result<Vec<usize>, UsageError> {
+        match input {
+            Input::File(_) | Input::Stdin if days.len() != 1 => {
+                self.error("--input requires exactly one day")
+            }
+            _ => Ok(all_days(days)),
+        }
+    }
+
+    fn unexpected<T>(&self, arg: &str) -> Result<T, UsageError> {
+        if arg.starts_with('-') {
+            self.error(format!("unknown option `{arg}`"))
+        } else {
+            self.error(format!("unexpected argument `{arg}`"))
+        }
+    }
+}
+
+fn all_days(days: Vec<usize>) -> Vec<usize> {
+    if days.is_empty() {
+        DAYS.collect()
+    } else {
+        days
+    }
+}
+
+fn help(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "run" => RUN,
+        "list" => LIST,
+        "bench" => BENCH,
+        "check" => CHECK,
+        "example" => EXAMPLE,
+        "disasm" => DISASM,
+        "diff" => DIFF,
+        "help" => HELP,
+        _ => return None,
+    })
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, UsageError> {
+    let mut args: Vec<String> = args.into_iter().collect();
+    let command = match args.first() {
+        None => "run".to_string(),
+        Some(first)
+            if first != "--help" && (first.starts_with("--") || first.parse::<usize>().is_ok()) =>
+        {
+            "run".to_string()
+        }
+        Some(_) => args.remove(0),
+    };
+    if matches!(command.as_str(), "-h" | "--help") {
+        return Ok(Command::Help(USAGE));
+    }
+    let Some(help) = help(&command) else {
+        return Err(UsageError {
+            message: format!("unknown command `{command}`"),
+            help: USAGE,
+        });
+    };
+    if command != "help" && args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help(help));
+    }
+
+    let mut args = Args {
+        args: args.into_iter(),
+        help,
+    };
+    match command.as_str() {
+        "run" => parse_run(args),
+        "list" => match args.args.next() {
+            Some(arg) => args.unexpected(&arg),
+            None => Ok(Command::List),
+        },
+        "bench" => parse_bench(args),
+        "check" => {
+            let mut days = Vec::new();
+            let mut input = Input::Dir;
+            let mut save = false;
+            while let Some(arg) = args.args.next() {
+                match arg.as_str() {
+                    _ if args.input(&arg, &mut input)? => {}
+                    "--save" => save = true,
+                    _ if arg.starts_with('-') => return args.unexpected(&arg),
+                    _ => days.extend(args.days(&arg)?),
+                }
+            }
+            Ok(Command::Check {
+                days: args.days_for(days, &input)?,
+                input,
+                save,
+            })
+        }
+        "example" => {
+            let mut days = Vec::new();
+            while let Some(arg) = args.args.next() {
+                if arg.starts_with('-') {
+                    return args.unexpected(&arg);
+                }
+                days.extend(args.days(&arg)?);
+            }
+            Ok(Command::Example {
+                days: all_days(days),
+            })
+        }
+        "disasm" => parse_disasm(args),
+        "diff" => {
+            let (Some(left), Some(right)) = (args.args.next(), args.args.next()) else {
+                return args.error("diff requires two transcript paths");
+            };
+            match args.args.next() {
+                Some(arg) => args.unexpected(&arg),
+                None => Ok(Command::Diff { left, right }),
+            }
+        }
+        _ => match args.args.next() {
+            None => Ok(Command::Help(USAGE)),
+            Some(command) => match (self::help(&command), args.args.next()) {
+                (Some(help), None) => Ok(Command::Help(help)),
+                (None, _) => args.error(format!("unknown command `{command}`")),
+                (Some(_), Some(arg)) => args.unexpected(&arg),
+            },
+        },
+    }
+}
+
+fn parse_run(mut args: Args) -> Result<Command, UsageError> {
+    let mut run = Run {
+        days: Vec::new(),
+        input: Input::Dir,
+        part: None,
+        jobs: 1,
+        format: Format::Text,
+        patch: None,
+        record: None,
+        replay: None,
+    };
+    while let Some(arg) = args.args.next() {
+        match arg.as_str() {
+            _ if args.input(&arg, &mut run.input)? => {}
+            "--part" => {
+                let value = args.value(&arg)?;
+                match value.parse() {
+                    Ok(part) => run.part = Some(part),
+                    Err(err) => return args.error(err.to_string()),
+                }
+            }
+            "--jobs" => {
+                let value = args.value(&arg)?;
+                run.jobs = match value.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return args.error(format!("invalid job count `{value}`")),
+                };
+            }
+            "--format" => {
+                let value = args.value(&arg)?;
+                run.format = match value.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => {
+                        return args.error(format!(
+                            "invalid format `{value}`: expected text, json or csv"
+                        ))
+                    }
+                };
+            }
+            "--patch" => {
+                let value = args.value(&arg)?;
+                match value.parse() {
+                    Ok(patch) => run.patch = Some(patch),
+                    Err(err) => return args.error(err.to_string()),
+                }
+            }
+            "--patch-file" => {
+                let path = args.value(&arg)?;
+                match Patch::from_file(&path) {
+                    Ok(patch) => run.patch = Some(patch),
+                    Err(err) => return args.error(err.to_string()),
+                }
+            }
+            "--record" => run.record = Some(args.value(&arg)?),
+            "--replay" => run.replay = Some(args.value(&arg)?),
+            _ if arg.starts_with('-') => return args.unexpected(&arg),
+            _ => run.days.extend(args.days(&arg)?),
+        }
+    }
+    if run.record.is_some() && run.replay.is_some() {
+        return args.error("--record and --replay cannot be combined");
+    }
+    // Days solved side by side would interleave their events.
+    if run.jobs > 1 && (run.record.is_some() || run.replay.is_some()) {
+        return args.error("--record and --replay need --jobs 1");
+    }
+    // Patches only make sense for the Intcode days.
+    if run.patch.is_some() && run.days.is_empty() {
+        return args.error("--patch and --patch-file need the days to patch");
+    }
+    run.days = args.days_for(run.days, &run.input)?;
+    Ok(Command::Run(run))
+}
+
+fn parse_bench(mut args: Args) -> Result<Command, UsageError> {
+    let mut bench = Bench {
+        days: Vec::new(),
+        input: Input::Dir,
+        iterations: 10,
+        warmup: 3,
+        baseline: None,
+        save_baseline: None,
+        threshold: 0.1,
+    };
+    while let Some(arg) = args.args.next() {
+        match arg.as_str() {
+            _ if args.input(&arg, &mut bench.input)? => {}
+            "--iterations" => {
+                let value = args.value(&arg)?;
+                bench.iterations = match value.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return args.error(format!("invalid iteration count `{value}`")),
+                };
+            }
+            "--warmup" => {
+                let value = args.value(&arg)?;
+                bench.warmup = match value.parse() {
+                    Ok(n) => n,
+                    Err(_) => return args.error(format!("invalid warm-up count `{value}`")),
+                };
+            }
+            "--threshold" => {
+                let value = args.value(&arg)?;
+                bench.threshold = match value.trim_end_matches('%').parse::<f64>() {
+                    Ok(pct) if pct >= 0.0 => pct / 100.0,
+                    _ => return args.error(format!("invalid threshold `{value}`")),
+                };
+            }
+            "--baseline" => bench.baseline = Some(args.value(&arg)?),
+            "--save-baseline" => bench.save_baseline = Some(args.value(&arg)?),
+            _ if arg.starts_with('-') => return args.unexpected(&arg),
+            _ => bench.days.extend(args.days(&arg)?),
+        }
+    }
+    // Every user would overwrite the days of the one before.
+    if bench.save_baseline.is_some() && bench.input == Input::AllUsers {
+        return args.error("--save-baseline cannot be combined with --all-users");
+    }
+    bench.days = args.days_for(bench.days, &bench.input)?;
+    Ok(Command::Bench(bench))
+}
+
+fn parse_disasm(mut args: Args) -> Result<Command, UsageError> {
+    let mut source = None;
+    let mut optimize = false;
+    let mut view = View::Listing;
+    while let Some(arg) = args.args.next() {
+        match arg.as_str() {
+            "--optimize" => optimize = true,
+            "--decompile" if view == View::Listing => view = View::Decompile,
+            "--lint" if view == View::Listing => view = View::Lint,
+            "--decompile" | "--lint" => {
+                return args.error("--decompile and --lint cannot be combined")
+            }
+            _ if source.is_some() => return args.unexpected(&arg),
+            "-" => source = Some(Source::Stdin),
+            _ if arg.starts_with('-') => return args.unexpected(&arg),
+            _ => {
+                source = Some(match arg.parse() {
+                    Ok(day) if DAYS.contains(&day) => Source::Day(day),
+                    _ => Source::File(arg),
+                })
+            }
+        }
+    }
+    match source {
+        Some(source) => Ok(Command::Disasm(Disasm {
+            source,
+            optimize,
+            view,
+        })),
+        None => args.error("disasm requires a day or a file"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(line: &str) -> Result<Command, UsageError> {
+        parse(line.split_whitespace().map(String::from))
+    }
+
+    fn message(line: &str) -> String {
+        parse_str(line).unwrap_err().message
+    }
+
+    #[test]
+    fn test_parse() {
+        let Ok(Command::Run(run)) = parse_str("run 3 5-7 --part 2 --patch 1=12,2=2") else {
+            panic!("expected a run command");
+        };
+        assert_eq!(run.days, [3, 5, 6, 7]);
+        assert_eq!(run.part, Some(Part::Two));
+        assert_eq!(run.patch, Some("1=12,2=2".parse().unwrap()));
+
+        let Ok(Command::Run(run)) = parse_str("") else {
+            panic!("expected a run command");
+        };
+        assert_eq!(run.days, DAYS.collect::<Vec<_>>());
+        let Ok(Command::Run(run)) = parse_str("9") else {
+            panic!("expected a run command");
+        };
+        assert_eq!(run.days, [9]);
+
+        assert_eq!(parse_str("list"), Ok(Command::List));
+        assert_eq!(
+            parse_str("bench 12 --iterations 3 --baseline base.json --threshold 5%"),
+            Ok(Command::Bench(Bench {
+                days: vec![12],
+                input: Input::Dir,
+                iterations: 3,
+                warmup: 3,
+                baseline: Some("base.json".into()),
+                save_baseline: None,
+                threshold: 0.05,
+            }))
+        );
+        assert_eq!(
+            parse_str("disasm input.txt --optimize --lint"),
+            Ok(Command::Disasm(Disasm {
+                source: Source::File("input.txt".into()),
+                optimize: true,
+                view: View::Lint,
+            }))
+        );
+        assert_eq!(
+            parse_str("check --user alice 1-2 --save"),
+            Ok(Command::Check {
+                days: vec![1, 2],
+                input: Input::User("alice".into()),
+                save: true,
+            })
+        );
+        let Ok(Command::Run(run)) = parse_str("run 8 --input -") else {
+            panic!("expected a run command");
+        };
+        assert_eq!(run.input, Input::Stdin);
+        let Ok(Command::Run(run)) = parse_str("run --format csv --jobs 4") else {
+            panic!("expected a run command");
+        };
+        assert_eq!((run.format, run.jobs), (Format::Csv, 4));
+        assert_eq!(parse_str("help check"), Ok(Command::Help(CHECK)));
+        assert_eq!(parse_str("example 4 --help"), Ok(Command::Help(EXAMPLE)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(message("frobnicate"), "unknown command `frobnicate`");
+        assert_eq!(message("run 26"), "invalid day `26`: expected 1-25");
+        assert_eq!(message("run 7-3"), "empty day range `7-3`");
+        assert_eq!(message("run --part 3"), "invalid part `3`: expected 1 or 2");
+        assert_eq!(message("run --part"), "--part requires a value");
+        assert_eq!(message("check --fast"), "unknown option `--fast`");
+        assert_eq!(message("list 3"), "unexpected argument `3`");
+        assert_eq!(message("disasm"), "disasm requires a day or a file");
+        assert_eq!(message("diff a"), "diff requires two transcript paths");
+        assert_eq!(
+            message("run --patch 1=5"),
+            "--patch and --patch-file need the days to patch"
+        );
+        assert_eq!(
+            message("run --input day01.txt"),
+            "--input requires exactly one day"
+        );
+        assert_eq!(
+            message("bench --user alice --all-users"),
+            "--input, --user and --all-users cannot be combined"
+        );
+        assert_eq!(
+            message("bench --all-users --save-baseline base.json"),
+            "--save-baseline cannot be combined with --all-users"
+        );
+        assert_eq!(parse_str("bench --iterations 0").unwrap_err().help, BENCH);
+    }
+}
@@ -1,18 +1,20 @@
 // https://adventofcode.com/2019/day/1
 
+use std::str::FromStr;
+
+use crate::solution::{Day, Entry, Example, ParseError, Solution, SolveError};
+
 #[derive(Debug)]
 pub struct Input {
     masses: Vec<usize>,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let masses = value
-            .lines()
-            .map(str::parse)
-            .collect::<Result<_, _>>()
-            .unwrap();
-        Self { masses }
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let masses = s.lines().map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Self { masses })
     }
 }
 
@@ -43,6 +45,36 @@ pub fn part2(input: &Input) -> usize {
     input.masses.iter().copied().map(calc_fuel_req).sum()
 }
 
+pub struct Day01;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day01>::new();
+
+impl Solution for Day01 {
+    const DAY: usize = 1;
+    const TITLE: &'static str = "The Tyranny of the Rocket Equation";
+    const EXAMPLES: &'static [Example] = &[Example {
+        input: "12\n14\n1969\n100756\n",
+        part1: Some("34241"),
+        part2: Some("51316"),
+    }];
+
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<usize, SolveError> {
+        Ok(part2(input))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
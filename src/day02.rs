@@ -1,148 +1,106 @@
 // https://adventofcode.com/2019/day/2
 
+use std::str::FromStr;
+
+use crate::intcode::{Integer, Tape};
+use crate::solution::{self, Day, Entry, ParseError, Solution, SolveError};
+
 #[derive(Debug)]
 pub struct Input {
     tape: Tape,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let tape = Tape::from(value);
-        Self { tape }
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tape = Tape::from_str(s)?;
+        Ok(Self { tape })
     }
 }
 
-#[derive(PartialEq)]
-enum OpCode {
-    Add,
-    Mul,
-    Eof,
+fn gravity_assist_program(tape: &mut Tape) -> Result<(), SolveError> {
+    let (run_status, _) = tape.run_with(&[]);
+    solution::halted(run_status)
 }
 
-impl From<usize> for OpCode {
-    fn from(value: usize) -> Self {
-        match value {
-            1 => OpCode::Add,
-            2 => OpCode::Mul,
-            99 => OpCode::Eof,
-            _ => panic!("invalid opcode: {}", value),
-        }
-    }
-}
+pub fn part1(input: &Input) -> Result<Integer, SolveError> {
+    let mut tape = input.tape.clone();
 
-#[derive(Clone, Debug)]
-struct Tape {
-    mem: Vec<usize>,
-}
+    tape.set_unpatched(1, 12);
+    tape.set_unpatched(2, 2);
 
-impl From<&str> for Tape {
-    fn from(value: &str) -> Self {
-        let mem = value
-            .split(',')
-            .map(str::parse)
-            .collect::<Result<_, _>>()
-            .unwrap();
-        Self { mem }
-    }
+    gravity_assist_program(&mut tape)?;
+
+    Ok(tape.get(0))
 }
 
-impl Tape {
-    fn get(&self, pos: usize) -> usize {
-        let mem = self.mem.get(pos).expect("invalid get access to tape");
-        *mem
-    }
+pub fn part2(input: &Input) -> Result<Integer, SolveError> {
+    const TARGET: Integer = 19_690_720;
 
-    fn set(&mut self, pos: usize, value: usize) {
-        let mem = self.mem.get_mut(pos).expect("invalid set access to tape");
-        *mem = value;
-    }
-}
+    for (noun, verb) in (0..100).flat_map(|noun| (0..100).map(move |verb| (noun, verb))) {
+        let mut tape = input.tape.clone();
 
-impl ToString for Tape {
-    fn to_string(&self) -> String {
-        let strings: Vec<String> = self.mem.iter().map(ToString::to_string).collect();
-        strings.join(",")
-    }
-}
+        // A patched noun or verb stays fixed during the search.
+        tape.set_unpatched(1, noun);
+        tape.set_unpatched(2, verb);
+        let (noun, verb) = (tape.get(1), tape.get(2));
 
-fn gravity_assist_program(tape: &mut Tape) {
-    let mut pc = 0;
-
-    loop {
-        let opcode: OpCode = tape.get(pc).into();
-
-        match opcode {
-            OpCode::Add => {
-                let lhs_addr = tape.get(pc + 1);
-                let lhs = tape.get(lhs_addr);
-                let rhs_addr = tape.get(pc + 2);
-                let rhs = tape.get(rhs_addr);
-                let dst = tape.get(pc + 3);
-
-                let result = lhs + rhs;
-                tape.set(dst, result);
-
-                pc += 4;
-            }
-            OpCode::Mul => {
-                let lhs_addr = tape.get(pc + 1);
-                let lhs = tape.get(lhs_addr);
-                let rhs_addr = tape.get(pc + 2);
-                let rhs = tape.get(rhs_addr);
-                let dst = tape.get(pc + 3);
-
-                let result = lhs * rhs;
-                tape.set(dst, result);
-
-                pc += 4;
-            }
-            OpCode::Eof => break,
+        gravity_assist_program(&mut tape)?;
+
+        if tape.get(0) == TARGET {
+            return Ok(100 * noun + verb);
         }
     }
+    Err(SolveError::new(format!("no noun and verb produce {TARGET}")))
 }
 
-pub fn part1(input: &Input) -> usize {
-    let mut tape = input.tape.clone();
-
-    tape.set(1, 12);
-    tape.set(2, 2);
+pub struct Day02;
 
-    gravity_assist_program(&mut tape);
+pub const SOLUTION: &dyn Day = &Entry::<Day02>::new();
 
-    tape.get(0)
-}
+impl Solution for Day02 {
+    const DAY: usize = 2;
+    const TITLE: &'static str = "1202 Program Alarm";
 
-pub fn part2(input: &Input) -> usize {
-    const TARGET: usize = 19_690_720;
+    type Input = Input;
+    type Part1 = Integer;
+    type Part2 = Integer;
 
-    (0..100)
-        .flat_map(|noun| (0..100).map(move |verb| (noun, verb)))
-        .find_map(|(noun, verb)| {
-            let mut tape = input.tape.clone();
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
 
-            tape.set(1, noun);
-            tape.set(2, verb);
+    fn tape(input: &mut Input) -> Option<&mut Tape> {
+        Some(&mut input.tape)
+    }
 
-            gravity_assist_program(&mut tape);
+    fn part1(input: &Input) -> Result<Integer, SolveError> {
+        part1(input)
+    }
 
-            let output = tape.get(0);
-            if output == TARGET {
-                Some(100 * noun + verb)
-            } else {
-                None
-            }
-        })
-        .expect("unable to find target")
+    fn part2(input: &Input) -> Result<Integer, SolveError> {
+        part2(input)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn run_tape<I: Into<Input>>(value: I) -> String {
-        let mut input: Input = value.into();
-        gravity_assist_program(&mut input.tape);
-        input.tape.to_string()
+    use crate::solution::{Part, Setup};
+
+    fn run_tape(value: &str) -> String {
+        let mut input: Input = value.parse().unwrap();
+        gravity_assist_program(&mut input.tape).unwrap();
+        let strings: Vec<String> = input
+            .tape
+            .memory()
+            .to_vec()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        strings.join(",")
     }
 
     #[test]
@@ -152,4 +110,34 @@ mod tests {
         assert_eq!(run_tape("2,4,4,5,99,0"), "2,4,4,5,99,9801");
         assert_eq!(run_tape("1,1,1,4,99,5,6,0,99"), "30,1,1,4,2,5,6,0,99");
     }
+
+    #[test]
+    fn test_patch() {
+        // Adds [noun] and [verb] into [0].
+        const TAPE: &str = "1,0,0,0,99,0,0,0,0,0,0,0,7";
+        let part1 = |patch: Option<&str>| {
+            let setup = Setup {
+                patch: patch.map(|patch| patch.parse().unwrap()),
+                ..Setup::default()
+            };
+            let (answers, _) = SOLUTION.solve_timed(TAPE, Some(Part::One), &setup).unwrap();
+            answers.part1.unwrap()
+        };
+
+        assert_eq!(part1(None), "9");
+        assert_eq!(part1(Some("1=4,2=4")), "198");
+        assert_eq!(part1(Some("2=4")), "106");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            SOLUTION.solve("1,0,0,0,99", Some(Part::Two)),
+            Err(SolveError::new("no noun and verb produce 19690720"))
+        );
+        assert_eq!(
+            SOLUTION.solve("3,0,99", Some(Part::One)),
+            Err(SolveError::new("program waits for input at pc 0"))
+        );
+    }
 }
@@ -1,6 +1,9 @@
 // https://adventofcode.com/2019/day/3
 
 use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::solution::{Day, Entry, Example, ParseError, Solution, SolveError};
 
 type Coord = (isize, isize);
 
@@ -9,14 +12,22 @@ pub struct Input {
     wire2: Wire,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let mut lines = value.lines();
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let mut wire = || {
+            lines
+                .next()
+                .ok_or_else(|| ParseError::new("expected two wires"))?
+                .parse::<Wire>()
+        };
 
-        let wire1: Wire = lines.next().unwrap().into();
-        let wire2: Wire = lines.next().unwrap().into();
+        let wire1 = wire()?;
+        let wire2 = wire()?;
 
-        Self { wire1, wire2 }
+        Ok(Self { wire1, wire2 })
     }
 }
 
@@ -28,14 +39,16 @@ enum Direction {
     Right,
 }
 
-impl From<&str> for Direction {
-    fn from(value: &str) -> Self {
-        match value.chars().nth(0).unwrap() {
-            'U' => Direction::Up,
-            'D' => Direction::Down,
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            c => panic!("invalid Direction char: {c}"),
+impl FromStr for Direction {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "U" => Ok(Direction::Up),
+            "D" => Ok(Direction::Down),
+            "L" => Ok(Direction::Left),
+            "R" => Ok(Direction::Right),
+            _ => Err(ParseError::new(format!("invalid Direction: {s}"))),
         }
     }
 }
@@ -66,13 +79,18 @@ impl Wire {
     }
 }
 
-impl From<&str> for Wire {
-    fn from(value: &str) -> Self {
+impl FromStr for Wire {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut coords = Vec::new();
         let mut curr_coord = (0, 0);
-        for segment in value.split(',') {
-            let dir: Direction = segment[..1].into();
-            let len = segment[1..].parse().unwrap();
+        for segment in s.trim().split(',') {
+            let (dir, len) = segment
+                .split_at_checked(1)
+                .ok_or_else(|| ParseError::new(format!("invalid segment: {segment}")))?;
+            let dir: Direction = dir.parse()?;
+            let len = len.parse()?;
             let mut coord_step = || {
                 match dir {
                     Direction::Up => curr_coord.0 += 1,
@@ -86,7 +104,7 @@ impl From<&str> for Wire {
             curr_coord = *coords.last().unwrap();
         }
         let set: HashSet<Coord> = coords.iter().copied().collect();
-        Self { coords, set }
+        Ok(Self { coords, set })
     }
 }
 
@@ -115,6 +133,43 @@ pub fn part2(input: &Input) -> isize {
         .unwrap()
 }
 
+pub struct Day03;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day03>::new();
+
+impl Solution for Day03 {
+    const DAY: usize = 3;
+    const TITLE: &'static str = "Crossed Wires";
+    const EXAMPLES: &'static [Example] = &[
+        Example {
+            input: "R8,U5,L5,D3\nU7,R6,D4,L4\n",
+            part1: Some("6"),
+            part2: Some("30"),
+        },
+        Example {
+            input: "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83\n",
+            part1: Some("159"),
+            part2: Some("610"),
+        },
+    ];
+
+    type Input = Input;
+    type Part1 = isize;
+    type Part2 = isize;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<isize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<isize, SolveError> {
+        Ok(part2(input))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,7 +179,7 @@ mod tests {
         const INPUT: &str = r#"R75,D30,R83,U83,L12,D49,R71,U7,L72
 U62,R66,U55,R34,D71,R55,D58,R83"#;
 
-        assert_eq!(part1(&INPUT.into()), 159);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 159);
     }
 
     #[test]
@@ -132,7 +187,7 @@ U62,R66,U55,R34,D71,R55,D58,R83"#;
         const INPUT: &str = r#"R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51
 U98,R91,D20,R16,D67,R40,U7,R15,U6,R7"#;
 
-        assert_eq!(part1(&INPUT.into()), 135);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 135);
     }
 
     #[test]
@@ -140,7 +195,7 @@ U98,R91,D20,R16,D67,R40,U7,R15,U6,R7"#;
         const INPUT: &str = r#"R75,D30,R83,U83,L12,D49,R71,U7,L72
 U62,R66,U55,R34,D71,R55,D58,R83"#;
 
-        assert_eq!(part2(&INPUT.into()), 610);
+        assert_eq!(part2(&INPUT.parse().unwrap()), 610);
     }
 
     #[test]
@@ -148,6 +203,6 @@ U62,R66,U55,R34,D71,R55,D58,R83"#;
         const INPUT: &str = r#"R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51
 U98,R91,D20,R16,D67,R40,U7,R15,U6,R7"#;
 
-        assert_eq!(part2(&INPUT.into()), 410);
+        assert_eq!(part2(&INPUT.parse().unwrap()), 410);
     }
 }
@@ -1,19 +1,27 @@
 // https://adventofcode.com/2019/day/4
 
 use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::solution::{Day, Entry, ParseError, Solution, SolveError};
 
 #[derive(Debug)]
 pub struct Input {
     range: RangeInclusive<usize>,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let (from, to) = value.split_once('-').unwrap();
-        let from = from.parse().unwrap();
-        let to = to.parse().unwrap();
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| ParseError::new(format!("expected <from>-<to>: {s}")))?;
+        let from = from.parse()?;
+        let to = to.parse()?;
         let range = from..=to;
-        Self { range }
+        Ok(Self { range })
     }
 }
 
@@ -117,6 +125,31 @@ fn validate2(password: &Password) -> bool {
     adj_digits == AdjacentDigits::Ok && ascending
 }
 
+pub struct Day04;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day04>::new();
+
+impl Solution for Day04 {
+    const DAY: usize = 4;
+    const TITLE: &'static str = "Secure Container";
+
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<usize, SolveError> {
+        Ok(part2(input))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,220 +1,178 @@
 // https://adventofcode.com/2019/day/2
 
+use std::fmt;
+use std::str::FromStr;
+
+use crate::intcode::{Integer, Io, Tape};
+use crate::solution::{Day, Entry, ParseError, Solution, SolveError};
+
 #[derive(Debug)]
 pub struct Input {
     tape: Tape,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let tape = value.into();
-        Self { tape }
-    }
-}
-
-type Integer = isize;
-
-#[derive(Copy, Clone, PartialEq)]
-enum ParamMode {
-    Position,
-    Immediate,
-}
+impl FromStr for Input {
+    type Err = ParseError;
 
-impl From<Integer> for ParamMode {
-    fn from(value: Integer) -> Self {
-        match value {
-            0 => ParamMode::Position,
-            1 => ParamMode::Immediate,
-            _ => panic!("invalid ParamMode value: {value}"),
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tape = Tape::from_str(s)?;
+        Ok(Self { tape })
     }
 }
 
-#[derive(PartialEq)]
-enum OpCode {
-    Add(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs + rhs
-    Mul(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs * rhs
-    Input,                     // <op>,<dst>             : dst = *input*
-    Output(ParamMode),         // <op>,<src>             : *output* = src
-    JumpIfTrue(ParamMode, ParamMode), // <op>,<cnd>,<val>       : if cnd != 0 then pc = val
-    JumpIfFalse(ParamMode, ParamMode), // <op>,<cnd>,<val>       : if cnd == 0 then pc = val
-    LessThan(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs < rhs then dst = 1 else dst = 0
-    Equals(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs == rhs then dst = 1 else dst = 0
-    Eof,
-}
-
-impl From<Integer> for OpCode {
-    fn from(value: Integer) -> Self {
-        let _param3 = ParamMode::from((value / 10000) % 10);
-        let param2 = ParamMode::from((value / 1000) % 10);
-        let param1 = ParamMode::from((value / 100) % 10);
-        let opcode = value % 100;
-        match opcode {
-            1 => OpCode::Add(param1, param2),
-            2 => OpCode::Mul(param1, param2),
-            3 => OpCode::Input,
-            4 => OpCode::Output(param1),
-            5 => OpCode::JumpIfTrue(param1, param2),
-            6 => OpCode::JumpIfFalse(param1, param2),
-            7 => OpCode::LessThan(param1, param2),
-            8 => OpCode::Equals(param1, param2),
-            99 => OpCode::Eof,
-            _ => panic!("invalid OpCode value: {value}"),
+// Runs the program, answering every input with `system_id`, and returns each
+// output together with the pc of the instruction that produced it.
+fn gravity_assist_program(tape: &mut Tape, system_id: Integer) -> Vec<(Integer, Integer)> {
+    let mut output = Vec::new();
+    loop {
+        let pc = tape.pc();
+        let status = tape.run_limited(1, |io| match io {
+            Io::Input => Some(system_id),
+            Io::Output(value) => {
+                output.push((pc, value));
+                None
+            }
+        });
+        if status.is_halt() {
+            return output;
         }
     }
 }
 
-#[derive(Clone, Debug)]
-struct Tape {
-    mem: Vec<Integer>,
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub code: Integer,
+    pub pc: Integer,
+    pub tests_passed: usize,
 }
 
-impl From<&str> for Tape {
-    fn from(value: &str) -> Self {
-        let mem = value
-            .split(',')
-            .map(str::parse)
-            .collect::<Result<_, _>>()
-            .unwrap();
-        Self { mem }
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code)
     }
 }
 
-impl Tape {
-    fn get(&self, pos: usize) -> Integer {
-        let mem = self.mem.get(pos).expect("invalid get position");
-        *mem
-    }
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum DiagnosticError {
+    #[error("self-test {test} failed at pc {pc} with value {value}")]
+    TestFailed {
+        test: usize,
+        pc: Integer,
+        value: Integer,
+    },
+    #[error("diagnostic program produced no output")]
+    NoOutput,
+}
 
-    fn pget(&self, pos: usize, param: ParamMode) -> Integer {
-        let pos = match param {
-            ParamMode::Position => self.get(pos) as usize,
-            ParamMode::Immediate => pos,
-        };
-        self.get(pos)
+// Every output but the last is a self-test which must report 0, the last
+// output is the diagnostic code.
+fn run_diagnostic(tape: &Tape, system_id: Integer) -> Result<Diagnostic, DiagnosticError> {
+    let mut tape = tape.clone();
+
+    let output = gravity_assist_program(&mut tape, system_id);
+    let ((pc, code), tests) = output.split_last().ok_or(DiagnosticError::NoOutput)?;
+
+    for (test, &(pc, value)) in tests.iter().enumerate() {
+        if value != 0 {
+            return Err(DiagnosticError::TestFailed {
+                test: test + 1,
+                pc,
+                value,
+            });
+        }
     }
 
-    fn set(&mut self, pos: usize, value: Integer) {
-        let mem = self.mem.get_mut(pos).expect("invalid set pos");
-        *mem = value;
-    }
+    Ok(Diagnostic {
+        code: *code,
+        pc: *pc,
+        tests_passed: tests.len(),
+    })
 }
 
-impl ToString for Tape {
-    fn to_string(&self) -> String {
-        let strings: Vec<String> = self.mem.iter().map(ToString::to_string).collect();
-        strings.join(",")
+impl From<DiagnosticError> for SolveError {
+    fn from(value: DiagnosticError) -> Self {
+        SolveError::new(value.to_string())
     }
 }
 
-fn gravity_assist_program<I>(tape: &mut Tape, mut input: I) -> Vec<Integer>
-where
-    I: Iterator<Item = Integer>,
-{
-    let mut output = Vec::new();
-    let mut pc = 0;
-
-    loop {
-        let opcode: OpCode = tape.get(pc).into();
-
-        match opcode {
-            OpCode::Add(param1, param2) => {
-                let lhs = tape.pget(pc + 1, param1);
-                let rhs = tape.pget(pc + 2, param2);
-                let dst = tape.get(pc + 3);
-
-                let result = lhs + rhs;
-                tape.set(dst as usize, result);
-
-                pc += 4;
-            }
-            OpCode::Mul(param1, param2) => {
-                let lhs = tape.pget(pc + 1, param1);
-                let rhs = tape.pget(pc + 2, param2);
-                let dst = tape.get(pc + 3);
-
-                let result = lhs * rhs;
-                tape.set(dst as usize, result);
-
-                pc += 4;
-            }
-            OpCode::Input => {
-                let dst = tape.get(pc + 1);
-
-                let result = input.next().unwrap();
+pub fn part1(input: &Input) -> Result<Diagnostic, DiagnosticError> {
+    run_diagnostic(&input.tape, 1)
+}
 
-                tape.set(dst as usize, result);
+pub fn part2(input: &Input) -> Result<Diagnostic, DiagnosticError> {
+    run_diagnostic(&input.tape, 5)
+}
 
-                pc += 2;
-            }
-            OpCode::Output(param1) => {
-                let src = tape.pget(pc + 1, param1);
+pub struct Day05;
 
-                output.push(src);
+pub const SOLUTION: &dyn Day = &Entry::<Day05>::new();
 
-                pc += 2;
-            }
-            OpCode::JumpIfTrue(param1, param2) => {
-                let cnd = tape.pget(pc + 1, param1);
-                let val = tape.pget(pc + 2, param2);
-
-                if cnd != 0 {
-                    pc = val as usize;
-                } else {
-                    pc += 3;
-                }
-            }
-            OpCode::JumpIfFalse(param1, param2) => {
-                let cnd = tape.pget(pc + 1, param1);
-                let val = tape.pget(pc + 2, param2);
-
-                if cnd == 0 {
-                    pc = val as usize;
-                } else {
-                    pc += 3;
-                }
-            }
-            OpCode::LessThan(param1, param2) => {
-                let lhs = tape.pget(pc + 1, param1);
-                let rhs = tape.pget(pc + 2, param2);
-                let dst = tape.get(pc + 3);
+impl Solution for Day05 {
+    const DAY: usize = 5;
+    const TITLE: &'static str = "Sunny with a Chance of Asteroids";
 
-                let result = if lhs < rhs { 1 } else { 0 };
-                tape.set(dst as usize, result);
+    type Input = Input;
+    type Part1 = Diagnostic;
+    type Part2 = Diagnostic;
 
-                pc += 4;
-            }
-            OpCode::Equals(param1, param2) => {
-                let lhs = tape.pget(pc + 1, param1);
-                let rhs = tape.pget(pc + 2, param2);
-                let dst = tape.get(pc + 3);
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
 
-                let result = if lhs == rhs { 1 } else { 0 };
-                tape.set(dst as usize, result);
+    fn tape(input: &mut Input) -> Option<&mut Tape> {
+        Some(&mut input.tape)
+    }
 
-                pc += 4;
-            }
-            OpCode::Eof => break,
-        }
+    fn part1(input: &Input) -> Result<Diagnostic, SolveError> {
+        Ok(part1(input)?)
     }
 
-    output
+    fn part2(input: &Input) -> Result<Diagnostic, SolveError> {
+        Ok(part2(input)?)
+    }
 }
 
-pub fn part1(input: &Input) -> usize {
-    let mut tape = input.tape.clone();
-
-    let input = std::iter::repeat(1);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let output = gravity_assist_program(&mut tape, input);
-    output[0] as usize
-}
+    fn diagnostic(tape: &str, system_id: Integer) -> Result<Diagnostic, DiagnosticError> {
+        run_diagnostic(&tape.parse().unwrap(), system_id)
+    }
 
-pub fn part2(input: &Input) -> usize {
-    let mut tape = input.tape.clone();
+    #[test]
+    fn test_equal_to_8() {
+        const TAPE: &str = "3,9,8,9,10,9,4,9,99,-1,8";
+        assert_eq!(diagnostic(TAPE, 8).unwrap().code, 1);
+        assert_eq!(diagnostic(TAPE, 5).unwrap().code, 0);
+    }
 
-    let input = std::iter::repeat(5);
+    #[test]
+    fn test_self_tests() {
+        assert_eq!(
+            diagnostic("104,0,104,0,104,42,99", 1),
+            Ok(Diagnostic {
+                code: 42,
+                pc: 4,
+                tests_passed: 2,
+            })
+        );
+        assert_eq!(
+            diagnostic("104,0,104,3,104,42,99", 1),
+            Err(DiagnosticError::TestFailed {
+                test: 2,
+                pc: 2,
+                value: 3,
+            })
+        );
+        assert_eq!(diagnostic("99", 1), Err(DiagnosticError::NoOutput));
+    }
 
-    let output = gravity_assist_program(&mut tape, input);
-    output[0] as usize
+    #[test]
+    fn test_failure_is_reported() {
+        assert_eq!(
+            SOLUTION.solve("104,0,104,3,104,42,99", None),
+            Err(SolveError::new("self-test 2 failed at pc 2 with value 3"))
+        );
+    }
 }
@@ -4,15 +4,19 @@ use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::solution::{Day, Entry, Example, ParseError, Solution, SolveError};
+
 #[derive(Debug)]
 pub struct Input {
     map: OrbitMap,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let map = OrbitMap::from_str(value).unwrap();
-        Self { map }
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let map = OrbitMap::from_str(s)?;
+        Ok(Self { map })
     }
 }
 
@@ -109,14 +113,15 @@ impl OrbitMap {
 }
 
 impl FromStr for OrbitMap {
-    type Err = Box<dyn ::std::error::Error>;
+    type Err = ParseError;
 
-    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut orbits = HashMap::new();
         for line in s.lines() {
-            let mut iter = line.splitn(2, ')').map(|o| o.to_string());
-
-            let (o1_id, o2_id) = (iter.next().unwrap(), iter.next().unwrap());
+            let (o1_id, o2_id) = line
+                .split_once(')')
+                .ok_or_else(|| ParseError::new(format!("invalid orbit: {line}")))?;
+            let (o1_id, o2_id) = (o1_id.to_string(), o2_id.to_string());
             let mut o2 = Orbit::new();
             o2.set_direct(o1_id.clone());
 
@@ -135,6 +140,43 @@ pub fn part2(input: &Input) -> usize {
     input.map.traverse("YOU", "SAN")
 }
 
+pub struct Day06;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day06>::new();
+
+impl Solution for Day06 {
+    const DAY: usize = 6;
+    const TITLE: &'static str = "Universal Orbit Map";
+    const EXAMPLES: &'static [Example] = &[
+        Example {
+            input: "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\n",
+            part1: Some("42"),
+            part2: None,
+        },
+        Example {
+            input: "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN\n",
+            part1: None,
+            part2: Some("4"),
+        },
+    ];
+
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<usize, SolveError> {
+        Ok(part2(input))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -152,7 +194,7 @@ D)I
 E)J
 J)K
 K)L";
-        assert_eq!(part1(&INPUT.into()), 42);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 42);
     }
 
     #[test]
@@ -170,6 +212,6 @@ J)K
 K)L
 K)YOU
 I)SAN";
-        assert_eq!(part2(&INPUT.into()), 4);
+        assert_eq!(part2(&INPUT.parse().unwrap()), 4);
     }
 }
@@ -1,286 +1,458 @@
 // https://adventofcode.com/2019/day/7
 
+use std::cmp::Reverse;
 use std::collections::VecDeque;
+use std::iter;
+use std::ops::Range;
+use std::panic;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 use itertools::Itertools;
 
-type Integer = isize;
+use crate::intcode::{Integer, Io, Tape};
+use crate::solution::{self, Day, Entry, Example, ParseError, Solution, SolveError};
 
 pub struct Input {
     tape: Tape,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let tape = Tape::from_str(value).unwrap();
-        Self { tape }
-    }
-}
-
-#[derive(Copy, Clone, PartialEq)]
-enum ParamMode {
-    Position,
-    Immediate,
-}
-
-impl From<Integer> for ParamMode {
-    fn from(value: Integer) -> Self {
-        match value {
-            0 => ParamMode::Position,
-            1 => ParamMode::Immediate,
-            _ => panic!("invalid ParamMode value: {value}"),
-        }
-    }
-}
-
-#[derive(PartialEq)]
-enum OpCode {
-    Add(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs + rhs
-    Mul(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs * rhs
-    Input,                     // <op>,<dst>             : dst = *input*
-    Output(ParamMode),         // <op>,<src>             : *output* = src
-    JumpIfTrue(ParamMode, ParamMode), // <op>,<cnd>,<val>       : if cnd != 0 then pc = val
-    JumpIfFalse(ParamMode, ParamMode), // <op>,<cnd>,<val>       : if cnd == 0 then pc = val
-    LessThan(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs < rhs then dst = 1 else dst = 0
-    Equals(ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs == rhs then dst = 1 else dst = 0
-    Eof,
-}
+impl FromStr for Input {
+    type Err = ParseError;
 
-impl From<Integer> for OpCode {
-    fn from(value: Integer) -> Self {
-        let _param3: ParamMode = ((value / 10000) % 10).into();
-        let param2: ParamMode = ((value / 1000) % 10).into();
-        let param1: ParamMode = ((value / 100) % 10).into();
-        let opcode = value % 100;
-        match opcode {
-            1 => OpCode::Add(param1, param2),
-            2 => OpCode::Mul(param1, param2),
-            3 => OpCode::Input,
-            4 => OpCode::Output(param1),
-            5 => OpCode::JumpIfTrue(param1, param2),
-            6 => OpCode::JumpIfFalse(param1, param2),
-            7 => OpCode::LessThan(param1, param2),
-            8 => OpCode::Equals(param1, param2),
-            99 => OpCode::Eof,
-            _ => panic!("invalid OpCode value: {value}"),
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tape = Tape::from_str(s)?;
+        Ok(Self { tape })
     }
 }
 
-enum RunStatus {
-    Poll,
-    Halt,
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("line {line}: {message}")]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
 }
 
-#[derive(Clone)]
-struct Tape {
-    mem: Vec<Integer>,
-    pc: isize,
-    output: VecDeque<Integer>,
+// Amplifier network, described line by line:
+//
+//     amplifiers 5
+//     phases 5..10
+//     signal 0
+//     input 0
+//     output 4
+//     edge 0 -> 1 -> 2 -> 3 -> 4 -> 0
+//
+// Every amplifier first receives its phase setting. The initial signal is fed
+// to each `input` amplifier, every output is copied to all successors of the
+// amplifier that produced it, and the answer is the last value produced by
+// the `output` amplifier once the network halts or stalls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    amplifiers: usize,
+    phases: Range<Integer>,
+    signal: Integer,
+    input: Vec<usize>,
+    output: usize,
+    edges: Vec<(usize, usize)>,
 }
 
-impl Tape {
-    fn empty(&self) -> bool {
-        self.mem.is_empty()
-    }
-
-    fn pop_output(&mut self) -> Option<Integer> {
-        self.output.pop_front()
-    }
-
-    fn halted(&self) -> bool {
-        let opcode: OpCode = self.get(self.pc).into();
-        opcode == OpCode::Eof
-    }
-
-    fn get(&self, pos: Integer) -> Integer {
-        let mem = self.mem.get(pos as usize).expect("invalid get access");
-        *mem
-    }
-
-    fn pget(&mut self, pos: Integer, param: ParamMode) -> Integer {
-        let pos = match param {
-            ParamMode::Position => self.get(pos),
-            ParamMode::Immediate => pos,
-        };
-        self.get(pos)
+impl Network {
+    fn linear(phases: Range<Integer>) -> Self {
+        let amplifiers = phases.clone().count();
+        Self {
+            amplifiers,
+            phases,
+            signal: 0,
+            input: vec![0],
+            output: amplifiers - 1,
+            edges: (1..amplifiers).map(|amp| (amp - 1, amp)).collect(),
+        }
     }
 
-    fn set(&mut self, pos: Integer, value: Integer) {
-        let mem = self.mem.get_mut(pos as usize).expect("invalid set access");
-        *mem = value;
+    fn feedback_loop(phases: Range<Integer>) -> Self {
+        let mut network = Self::linear(phases);
+        network.edges.push((network.amplifiers - 1, 0));
+        network
     }
 
-    fn run<I>(&mut self, mut input: I) -> RunStatus
-    where
-        I: Iterator<Item = Integer>,
-    {
-        if self.empty() {
-            return RunStatus::Halt;
+    fn signal(&self, tape: &Tape, settings: &[Integer]) -> Option<Integer> {
+        let mut amps: Vec<Tape> = settings.iter().map(|_| tape.fork()).collect();
+        let mut queues: Vec<VecDeque<Integer>> = settings
+            .iter()
+            .map(|&phase| VecDeque::from([phase]))
+            .collect();
+        for &amp in &self.input {
+            queues[amp].push_back(self.signal);
         }
 
+        let mut halted = vec![false; self.amplifiers];
+        let mut last_signal = None;
         loop {
-            let opcode: OpCode = self.get(self.pc).into();
-
-            match opcode {
-                OpCode::Add(param1, param2) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = self.get(self.pc + 3);
-
-                    let result = lhs + rhs;
-                    self.set(dst, result);
-
-                    self.pc += 4;
-                }
-                OpCode::Mul(param1, param2) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = self.get(self.pc + 3);
-
-                    let result = lhs * rhs;
-                    self.set(dst, result);
-
-                    self.pc += 4;
+            let mut progress = false;
+            for amp in 0..self.amplifiers {
+                if halted[amp] {
+                    continue;
                 }
-                OpCode::Input => {
-                    let dst = self.get(self.pc + 1);
 
-                    match input.next() {
-                        Some(value) => self.set(dst, value),
-                        None => return RunStatus::Poll,
+                let queue = &mut queues[amp];
+                let queued = queue.len();
+                let mut output = Vec::new();
+                let status = amps[amp].run(|io| match io {
+                    Io::Input => queue.pop_front(),
+                    Io::Output(value) => {
+                        output.push(value);
+                        None
                     }
+                });
+                progress |= queues[amp].len() != queued || !output.is_empty();
+                halted[amp] = status.is_halt();
 
-                    self.pc += 2;
+                for value in output {
+                    if amp == self.output {
+                        last_signal = Some(value);
+                    }
+                    for &(_, next) in self.edges.iter().filter(|(from, _)| *from == amp) {
+                        queues[next].push_back(value);
+                    }
                 }
-                OpCode::Output(param1) => {
-                    let src = self.pget(self.pc + 1, param1);
-                    self.output.push_back(src);
+            }
 
-                    self.pc += 2;
-                }
-                OpCode::JumpIfTrue(param1, param2) => {
-                    let cnd = self.pget(self.pc + 1, param1);
-                    let val = self.pget(self.pc + 2, param2);
+            if halted.iter().all(|&halted| halted) || !progress {
+                return last_signal;
+            }
+        }
+    }
 
-                    self.pc = if cnd != 0 { val } else { self.pc + 3 };
-                }
-                OpCode::JumpIfFalse(param1, param2) => {
-                    let cnd = self.pget(self.pc + 1, param1);
-                    let val = self.pget(self.pc + 2, param2);
+    /// Tries every assignment of distinct phases to the amplifiers and
+    /// returns the first one, in permutation order, giving the highest signal.
+    pub fn search(&self, tape: &Tape) -> Option<(Vec<Integer>, Integer)> {
+        // A recorded search runs on one thread so that the transcript comes
+        // out in the same order every time.
+        let workers = match tape.recorder() {
+            Some(_) => 1,
+            None => solution::threads(),
+        };
+        self.search_with_workers(tape, workers)
+    }
 
-                    self.pc = if cnd == 0 { val } else { self.pc + 3 };
+    // The permutations are split by their first phase, and each worker takes
+    // the next first phase nobody has searched yet. The results are merged by
+    // signal and then by permutation index, so the answer does not depend on
+    // the number of workers.
+    fn search_with_workers(&self, tape: &Tape, workers: usize) -> Option<(Vec<Integer>, Integer)> {
+        let phases: Vec<Integer> = self.phases.clone().collect();
+        let next = AtomicUsize::new(0);
+        let worker = || {
+            let mut best: Option<((usize, usize), Vec<Integer>, Integer)> = None;
+            let mut first = next.fetch_add(1, Ordering::Relaxed);
+            while let Some(&phase) = phases.get(first) {
+                let mut rest = phases.clone();
+                rest.remove(first);
+                let tails = rest.into_iter().permutations(self.amplifiers - 1);
+                for (index, tail) in tails.enumerate() {
+                    let settings: Vec<Integer> = iter::once(phase).chain(tail).collect();
+                    if let Some(signal) = self.signal(tape, &settings) {
+                        if best.as_ref().is_none_or(|(_, _, max)| signal > *max) {
+                            best = Some(((first, index), settings, signal));
+                        }
+                    }
                 }
-                OpCode::LessThan(param1, param2) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = self.get(self.pc + 3);
+                first = next.fetch_add(1, Ordering::Relaxed);
+            }
+            best
+        };
 
-                    let result = if lhs < rhs { 1 } else { 0 };
-                    self.set(dst, result);
+        let best = thread::scope(|scope| {
+            let handles: Vec<_> = (1..workers.min(phases.len()))
+                .map(|_| scope.spawn(worker))
+                .collect();
+            let mut results = vec![worker()];
+            // Re-raise a worker's panic as is, so that its message is kept.
+            results.extend(handles.into_iter().map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+            }));
+            results
+                .into_iter()
+                .flatten()
+                .max_by_key(|&(index, _, signal)| (signal, Reverse(index)))
+        });
+
+        best.map(|(_, settings, signal)| (settings, signal))
+    }
+}
 
-                    self.pc += 4;
+impl FromStr for Network {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut amplifiers = None;
+        let mut phases = None;
+        let mut signal = 0;
+        let mut input = Vec::new();
+        let mut output = None;
+        let mut edges = Vec::new();
+
+        for (line, content) in s.lines().enumerate() {
+            let line = line + 1;
+            let error = |message: String| ConfigError { line, message };
+            let number = |value: &str| {
+                value
+                    .trim()
+                    .parse::<Integer>()
+                    .map_err(|_| error(format!("invalid number: {value}")))
+            };
+            let amp = |value: &str| {
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| error(format!("invalid amplifier: {value}")))
+            };
+
+            let content = content.split('#').next().unwrap().trim();
+            if content.is_empty() {
+                continue;
+            }
+            let (key, value) = content.split_once(' ').unwrap_or((content, ""));
+            match key {
+                "amplifiers" => amplifiers = Some(amp(value)?),
+                "phases" => {
+                    let (start, end) = value
+                        .split_once("..")
+                        .ok_or_else(|| error(format!("expected <start>..<end>: {value}")))?;
+                    phases = Some(number(start)?..number(end)?);
                 }
-                OpCode::Equals(param1, param2) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = self.get(self.pc + 3);
-
-                    let result = if lhs == rhs { 1 } else { 0 };
-                    self.set(dst, result);
-
-                    self.pc += 4;
+                "signal" => signal = number(value)?,
+                "input" => {
+                    for value in value.split_whitespace() {
+                        input.push(amp(value)?);
+                    }
+                }
+                "output" => output = Some(amp(value)?),
+                "edge" => {
+                    let amps = value.split("->").map(amp).collect::<Result<Vec<_>, _>>()?;
+                    if amps.len() < 2 {
+                        return Err(error(format!("expected <from> -> <to>: {value}")));
+                    }
+                    edges.extend(amps.windows(2).map(|pair| (pair[0], pair[1])));
                 }
-                OpCode::Eof => return RunStatus::Halt,
+                _ => return Err(error(format!("unknown key: {key}"))),
             }
         }
-    }
-}
 
-impl FromStr for Tape {
-    type Err = Box<dyn ::std::error::Error>;
+        let missing = |key: &str| ConfigError {
+            line: 0,
+            message: format!("missing `{key}`"),
+        };
+        let amplifiers = amplifiers.ok_or_else(|| missing("amplifiers"))?;
+        let phases = phases.ok_or_else(|| missing("phases"))?;
+        let count = phases.clone().count();
+        if count < amplifiers {
+            return Err(ConfigError {
+                line: 0,
+                message: format!("{count} phases for {amplifiers} amplifiers"),
+            });
+        }
+        let output = output.unwrap_or(amplifiers.saturating_sub(1));
+        if input.is_empty() {
+            input.push(0);
+        }
+
+        let out_of_range = input
+            .iter()
+            .chain([&output])
+            .chain(edges.iter().flat_map(|(from, to)| [from, to]))
+            .find(|&&amp| amp >= amplifiers);
+        if let Some(amp) = out_of_range {
+            return Err(ConfigError {
+                line: 0,
+                message: format!("amplifier {amp} out of range 0..{amplifiers}"),
+            });
+        }
 
-    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
         Ok(Self {
-            mem: s
-                .split(',')
-                .map(|i| i.parse())
-                .collect::<::std::result::Result<_, _>>()
-                .unwrap(),
-            pc: 0,
-            output: VecDeque::new(),
+            amplifiers,
+            phases,
+            signal,
+            input,
+            output,
+            edges,
         })
     }
 }
 
-pub fn part1(input: &Input) -> Integer {
-    find_max_signal(&input.tape)
+fn max_signal(network: Network, tape: &Tape) -> Result<Integer, SolveError> {
+    let (_, signal) = network
+        .search(tape)
+        .ok_or_else(|| SolveError::new("no phase settings produce a signal"))?;
+    Ok(signal)
 }
 
-pub fn part2(input: &Input) -> Integer {
-    find_max_signal_feedback_loop(&input.tape)
+pub fn part1(input: &Input) -> Result<Integer, SolveError> {
+    max_signal(Network::linear(0..5), &input.tape)
 }
 
-fn find_max_signal(tape: &Tape) -> Integer {
-    let mut max_thrust = 0;
+pub fn part2(input: &Input) -> Result<Integer, SolveError> {
+    max_signal(Network::feedback_loop(5..10), &input.tape)
+}
 
-    for settings in (0..5).permutations(5) {
-        let signal = find_signal(tape.clone(), settings.as_slice());
-        if signal > max_thrust {
-            max_thrust = signal;
-        }
+pub struct Day07;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day07>::new();
+
+impl Solution for Day07 {
+    const DAY: usize = 7;
+    const TITLE: &'static str = "Amplification Circuit";
+    const EXAMPLES: &'static [Example] = &[
+        Example {
+            input: "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0",
+            part1: Some("43210"),
+            part2: None,
+        },
+        Example {
+            input: "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+            part1: None,
+            part2: Some("139629729"),
+        },
+    ];
+
+    type Input = Input;
+    type Part1 = Integer;
+    type Part2 = Integer;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
     }
 
-    max_thrust
+    fn tape(input: &mut Input) -> Option<&mut Tape> {
+        Some(&mut input.tape)
+    }
+
+    fn part1(input: &Input) -> Result<Integer, SolveError> {
+        part1(input)
+    }
+
+    fn part2(input: &Input) -> Result<Integer, SolveError> {
+        part2(input)
+    }
 }
 
-fn find_max_signal_feedback_loop(tape: &Tape) -> Integer {
-    let mut max_thrust = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for settings in (5..10).permutations(5) {
-        let signal = find_signal_feedback_loop(tape.clone(), settings.as_slice());
-        if signal > max_thrust {
-            max_thrust = signal;
-        }
+    #[test]
+    fn test_part1_ex1() {
+        const INPUT: &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+        assert_eq!(part1(&INPUT.parse().unwrap()), Ok(43210));
     }
 
-    max_thrust
-}
+    #[test]
+    fn test_part1_ex2() {
+        const INPUT: &str =
+            "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0";
+        assert_eq!(part1(&INPUT.parse().unwrap()), Ok(54321));
+    }
 
-fn find_signal(tape: Tape, settings: &[Integer]) -> Integer {
-    let mut signal = 0;
-    for setting in settings {
-        let mut amp = tape.clone();
-        amp.run([*setting, signal].into_iter());
-        signal = amp
-            .pop_output()
-            .expect("Expected at least one output from tape");
+    #[test]
+    fn test_no_signal() {
+        // Halts without producing any output.
+        assert_eq!(
+            part1(&"99".parse().unwrap()),
+            Err(SolveError::new("no phase settings produce a signal"))
+        );
     }
-    signal
-}
 
-fn find_signal_feedback_loop(tape: Tape, settings: &[Integer]) -> Integer {
-    let mut amps: Vec<(Integer, Tape)> = settings
-        .iter()
-        .map(|setting| (*setting, tape.clone()))
-        .collect();
-    for (setting, amp) in amps.iter_mut() {
-        amp.run([*setting].into_iter());
+    #[test]
+    fn test_part2_ex1() {
+        const INPUT: &str =
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+        assert_eq!(part2(&INPUT.parse().unwrap()), Ok(139629729));
     }
 
-    let mut last_signal = 0;
-    loop {
-        let mut signal = last_signal;
-        for (_, amp) in amps.iter_mut() {
-            if amp.halted() {
-                return last_signal;
-            }
-            amp.run([signal].into_iter());
-            signal = amp
-                .pop_output()
-                .expect("Expected at least one output from tape");
+    #[test]
+    fn test_network_config() {
+        const CONFIG: &str = r#"
+            # Amplifier 0 fans out to 1 and 2, only 2 is observed.
+            amplifiers 3
+            phases 0..3
+            signal 10
+            input 0
+            output 2
+            edge 0 -> 1
+            edge 0 -> 2
+        "#;
+        // Adds its phase setting to its input signal.
+        const TAPE: &str = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+
+        let network = Network::from_str(CONFIG).unwrap();
+        assert_eq!(network, {
+            let mut linear = Network::linear(0..3);
+            linear.signal = 10;
+            linear.edges = vec![(0, 1), (0, 2)];
+            linear
+        });
+
+        let tape = Tape::from_str(TAPE).unwrap();
+        assert_eq!(network.search(&tape), Some((vec![1, 0, 2], 13)));
+    }
+
+    #[test]
+    fn test_search_is_deterministic() {
+        const TAPE: &str = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+        let tape = Tape::from_str(TAPE).unwrap();
+
+        // Only the phases of the first two amplifiers matter, so four
+        // assignments tie and the first of them must win.
+        let network =
+            Network::from_str("amplifiers 4\nphases 0..4\noutput 1\nedge 0 -> 1").unwrap();
+        for workers in 1..=8 {
+            assert_eq!(
+                network.search_with_workers(&tape, workers),
+                Some((vec![2, 3, 0, 1], 5)),
+            );
         }
-        last_signal = signal;
+    }
+
+    #[test]
+    fn test_search_is_recorded() {
+        use crate::intcode::transcript::Recorder;
+
+        const TAPE: &str = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+        let network =
+            Network::from_str("amplifiers 4\nphases 0..4\noutput 1\nedge 0 -> 1").unwrap();
+        let search = |recorder: &Recorder| {
+            let mut tape = Tape::from_str(TAPE).unwrap();
+            tape.record(recorder.clone());
+            network.search(&tape)
+        };
+
+        let recorder = Recorder::new();
+        let best = search(&recorder);
+        let transcript = recorder.transcript();
+        let replayer = Recorder::replay(&transcript);
+        assert_eq!(search(&replayer), best);
+        assert_eq!(transcript.diff(&replayer.transcript()), None);
+    }
+
+    #[test]
+    fn test_network_config_errors() {
+        let line = |config: &str| Network::from_str(config).unwrap_err().line;
+        assert_eq!(line("amplifiers 2\nphases 0-2"), 2);
+        assert_eq!(line("amplifiers 2\nphases 0..2\nedge 0 -> x"), 3);
+        assert_eq!(line("amplifiers 2\nphases 0..2\nfoo 1"), 3);
+        assert_eq!(line("phases 0..2"), 0);
+        assert_eq!(line("amplifiers 2\nphases 0..2\nedge 0 -> 2"), 0);
+        assert_eq!(line("amplifiers -1\nphases 0..2"), 1);
+        assert_eq!(line("amplifiers 2\nphases 0..2\noutput -1"), 3);
+        assert_eq!(
+            Network::from_str("amplifiers 3\nphases 0..2"),
+            Err(ConfigError {
+                line: 0,
+                message: "2 phases for 3 amplifiers".into(),
+            })
+        );
     }
 }
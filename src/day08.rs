@@ -2,9 +2,12 @@
 
 use std::fmt;
 use std::io::Write;
+use std::str::FromStr;
 
 use termcolor::WriteColor;
 
+use crate::solution::{Day, Entry, ParseError, Solution, SolveError};
+
 const WIDTH: usize = 25;
 const HEIGHT: usize = 6;
 const AREA: usize = WIDTH * HEIGHT;
@@ -13,10 +16,12 @@ pub struct Input {
     image: String,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let image = value.to_string();
-        Self { image }
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let image = s.to_string();
+        Ok(Self { image })
     }
 }
 
@@ -89,7 +94,7 @@ impl fmt::Display for Layer {
 
 pub fn part1(input: &Input) -> usize {
     let colors = parse_colors(&input.image);
-    assert!(colors.len() % AREA == 0);
+    assert_eq!(colors.len() % AREA, 0);
 
     let layer = colors
         .as_slice()
@@ -103,7 +108,7 @@ pub fn part1(input: &Input) -> usize {
 
 pub fn part2(input: &Input) -> Layer {
     let colors = parse_colors(&input.image);
-    assert!(colors.len() % AREA == 0);
+    assert_eq!(colors.len() % AREA, 0);
 
     let layers: Vec<Layer> = colors
         .as_slice()
@@ -141,3 +146,28 @@ fn parse_colors(input: &str) -> Vec<Color> {
         .map(Color::from)
         .collect()
 }
+
+pub struct Day08;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day08>::new();
+
+impl Solution for Day08 {
+    const DAY: usize = 8;
+    const TITLE: &'static str = "Space Image Format";
+
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = Layer;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<Layer, SolveError> {
+        Ok(part2(input))
+    }
+}
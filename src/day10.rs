@@ -1,25 +1,34 @@
 use std::{
     cmp::Reverse,
     collections::{HashMap, HashSet},
+    str::FromStr,
 };
 
 use ndarray::Array2;
 
+use crate::solution::{Day, Entry, ParseError, Solution, SolveError};
+
 #[derive(Debug)]
 pub struct Input {
     map: Array2<char>,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let mut lines = value.lines().peekable();
-        let width = lines.peek().unwrap().len();
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().peekable();
+        let width = lines
+            .peek()
+            .ok_or_else(|| ParseError::new("empty map"))?
+            .len();
         let height = lines.count();
 
-        let map = value.lines().flat_map(str::chars).collect();
-        let map = Array2::from_shape_vec((height, width), map).unwrap();
+        let map = s.lines().flat_map(str::chars).collect();
+        let map = Array2::from_shape_vec((height, width), map)
+            .map_err(|err| ParseError::new(format!("map is not rectangular: {err}")))?;
 
-        Self { map }
+        Ok(Self { map })
     }
 }
 
@@ -173,3 +182,28 @@ pub fn part2(input: &Input) -> isize {
 
     unimplemented!("part2")
 }
+
+pub struct Day10;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day10>::new();
+
+impl Solution for Day10 {
+    const DAY: usize = 10;
+    const TITLE: &'static str = "Monitoring Station";
+
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = isize;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<isize, SolveError> {
+        Ok(part2(input))
+    }
+}
@@ -1,13 +1,22 @@
 use std::ops::AddAssign;
+use std::str::FromStr;
+
+use crate::solution::{Day, Entry, ParseError, Solution, SolveError};
 
 pub struct Input {
     moons: Vec<Moon>,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let moons = value.lines().map(str::trim).map(Moon::from).collect();
-        Self { moons }
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let moons = s
+            .lines()
+            .map(str::trim)
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { moons })
     }
 }
 
@@ -52,16 +61,25 @@ impl Moon {
     }
 }
 
-impl From<&str> for Moon {
-    fn from(value: &str) -> Self {
-        let value = value.strip_prefix('<').unwrap().strip_suffix('>').unwrap();
-        let mut tokens = value.split(", ").map(|s| s[2..].parse().unwrap());
-        let x = tokens.next().unwrap();
-        let y = tokens.next().unwrap();
-        let z = tokens.next().unwrap();
+impl FromStr for Moon {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::new(format!("invalid moon: {s}"));
+        let value = s
+            .strip_prefix('<')
+            .and_then(|value| value.strip_suffix('>'))
+            .ok_or_else(invalid)?;
+        let tokens = value
+            .split(", ")
+            .map(|token| Ok(token.get(2..).ok_or_else(invalid)?.parse()?))
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        let [x, y, z] = tokens[..] else {
+            return Err(invalid());
+        };
         let pos = Vector { x, y, z };
         let vel = Vector::default();
-        Self { pos, vel }
+        Ok(Self { pos, vel })
     }
 }
 
@@ -146,6 +164,31 @@ pub fn part2(input: &Input) -> usize {
     unreachable!()
 }
 
+pub struct Day12;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day12>::new();
+
+impl Solution for Day12 {
+    const DAY: usize = 12;
+    const TITLE: &'static str = "The N-Body Problem";
+
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<usize, SolveError> {
+        Ok(part2(input))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -157,7 +200,7 @@ mod test {
         <x=4, y=-8, z=8>
         <x=3, y=5, z=-1>"#;
 
-        let mut input: Input = INPUT.into();
+        let mut input: Input = INPUT.parse().unwrap();
         assert_eq!(simulate(&mut input.moons, 10), 179);
     }
 
@@ -168,7 +211,7 @@ mod test {
         <x=2, y=-7, z=3>
         <x=9, y=-8, z=-3>"#;
 
-        let mut input: Input = INPUT.into();
+        let mut input: Input = INPUT.parse().unwrap();
         assert_eq!(simulate(&mut input.moons, 100), 1940);
     }
 
@@ -179,7 +222,7 @@ mod test {
         <x=4, y=-8, z=8>
         <x=3, y=5, z=-1>"#;
 
-        let input: Input = INPUT.into();
+        let input: Input = INPUT.parse().unwrap();
         assert_eq!(part2(&input), 2772);
     }
 
@@ -190,7 +233,7 @@ mod test {
         <x=2, y=-7, z=3>
         <x=9, y=-8, z=-3>"#;
 
-        let input: Input = INPUT.into();
+        let input: Input = INPUT.parse().unwrap();
         assert_eq!(part2(&input), 4686774924);
     }
 }
@@ -1,360 +1,144 @@
-use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::intcode::frame::{Frame, Frames, Screen};
+use crate::intcode::{Integer, Io, Tape};
+use crate::solution::{self, Day, Entry, ParseError, Solution, SolveError};
 
 #[derive(Debug)]
 pub struct Input {
     tape: Tape,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let tape = Tape::from(value);
-        Self { tape }
-    }
-}
-
-type Integer = isize;
-
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum ParamMode {
-    Position,
-    Immediate,
-    Relative,
-}
-
-impl From<Integer> for ParamMode {
-    fn from(value: Integer) -> Self {
-        match value {
-            0 => ParamMode::Position,
-            1 => ParamMode::Immediate,
-            2 => ParamMode::Relative,
-            _ => panic!("invalid ParamMode value: {value}"),
-        }
-    }
-}
-
-#[derive(Debug, PartialEq)]
-enum OpCode {
-    Add(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs + rhs
-    Mul(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs * rhs
-    Input(ParamMode),                     // <op>,<dst>             : dst = *input*
-    Output(ParamMode),                    // <op>,<src>             : *output* = src
-    JumpIfTrue(ParamMode, ParamMode),     // <op>,<cnd>,<val>       : if cnd != 0 then pc = val
-    JumpIfFalse(ParamMode, ParamMode),    // <op>,<cnd>,<val>       : if cnd == 0 then pc = val
-    LessThan(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs < rhs then dst = 1 else dst = 0
-    Equals(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs == rhs then dst = 1 else dst = 0
-    AdjustRelBase(ParamMode),                // <op>,<adj>              : relbase += adj
-    Eof,
-}
+impl FromStr for Input {
+    type Err = ParseError;
 
-impl From<Integer> for OpCode {
-    fn from(value: Integer) -> Self {
-        let param3: ParamMode = ((value / 10000) % 10).into();
-        let param2: ParamMode = ((value / 1000) % 10).into();
-        let param1: ParamMode = ((value / 100) % 10).into();
-        let opcode = value % 100;
-        match opcode {
-            1 => OpCode::Add(param1, param2, param3),
-            2 => OpCode::Mul(param1, param2, param3),
-            3 => OpCode::Input(param1),
-            4 => OpCode::Output(param1),
-            5 => OpCode::JumpIfTrue(param1, param2),
-            6 => OpCode::JumpIfFalse(param1, param2),
-            7 => OpCode::LessThan(param1, param2, param3),
-            8 => OpCode::Equals(param1, param2, param3),
-            9 => OpCode::AdjustRelBase(param1),
-            99 => OpCode::Eof,
-            _ => panic!("invalid OpCode value: {value}"),
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tape = Tape::from_str(s)?;
+        Ok(Self { tape })
     }
 }
 
-#[derive(Debug, PartialEq)]
-enum RunStatus {
-    Poll,
-    Halt,
-}
-
-#[derive(Clone, Debug)]
-struct Tape {
-    mem: Vec<Integer>,
-    pc: Integer,
-    relbase: Integer,
-}
+const SCORE: Integer = -1;
 
-impl From<&str> for Tape {
-    fn from(value: &str) -> Self {
-        let mem = value
-            .split(',')
-            .map(str::parse)
-            .collect::<Result<_, _>>()
-            .unwrap();
-        Self {
-            mem,
-            pc: 0,
-            relbase: 0,
-        }
+fn tile(value: Integer) -> Result<char, SolveError> {
+    match value {
+        0 => Ok(' '),
+        1 => Ok('|'),
+        2 => Ok('#'),
+        3 => Ok('-'),
+        4 => Ok('*'),
+        _ => Err(SolveError::new(format!("invalid tile value: {value}"))),
     }
 }
 
-enum Io {
-    Input,
-    Output(Integer),
+fn arcade() -> Frames {
+    Frames::new(3).special(&[SCORE, 0])
 }
 
-impl Tape {
-    fn empty(&self) -> bool {
-        self.mem.is_empty()
-    }
-
-    fn get(&mut self, pos: Integer) -> Integer {
-        assert!(pos >= 0);
+pub fn part1(input: &Input) -> Result<usize, SolveError> {
+    let mut error = None;
+    let mut screen = Screen::default();
+    let mut frames = arcade();
 
-        if pos >= self.mem.len() as isize {
-            let new_len = (pos + 1) as usize;
-            self.mem.resize(new_len, 0);
+    let mut tape = input.tape.clone();
+    let status = tape.run(|io| match io {
+        Io::Input => None,
+        Io::Output(value) => {
+            if let Some(Frame::Record(record)) = frames.push(value) {
+                match tile(record[2]) {
+                    Ok(c) => screen.set((record[0], record[1]), c),
+                    Err(err) => {
+                        error.get_or_insert(err);
+                    }
+                }
+            }
+            None
         }
+    });
 
-        self.mem[pos as usize]
-    }
-
-    fn pget(&mut self, pos: Integer, param: ParamMode) -> Integer {
-        let pos = match param {
-            ParamMode::Position => self.get(pos),
-            ParamMode::Immediate => pos,
-            ParamMode::Relative => self.relbase + self.get(pos),
-        };
-        self.get(pos)
+    if let Some(error) = error {
+        return Err(error);
     }
+    solution::halted(status)?;
 
-    fn set(&mut self, pos: Integer, value: Integer) {
-        assert!(pos >= 0);
-
-        if pos >= self.mem.len() as isize {
-            let new_len = (pos + 1) as usize;
-            self.mem.resize(new_len, 0);
-        }
+    Ok(screen.values().filter(|&&tile| tile == '#').count())
+}
 
-        self.mem[pos as usize] = value;
-    }
+pub fn part2(input: &Input) -> Result<usize, SolveError> {
+    let mut error = None;
+    let mut screen = Screen::default();
+    let mut frames = arcade();
+    let mut ball: (Integer, Integer) = (0, 0);
+    let mut paddle: (Integer, Integer) = (0, 0);
+    let mut score = 0;
 
-    fn run<F>(&mut self, mut io: F) -> RunStatus
-    where
-        F: FnMut(Io) -> Option<Integer>,
-    {
-        if self.empty() {
-            return RunStatus::Halt;
+    let mut tape = input.tape.clone();
+    tape.set_unpatched(0, 2);
+    let status = tape.run(|io| match io {
+        // Stop at the next input once the screen makes no sense.
+        Io::Input if error.is_some() => None,
+        Io::Input => {
+            let signum = (ball.0 - paddle.0).signum();
+            Some(signum)
         }
-
-        loop {
-            let opcode: OpCode = self.get(self.pc).into();
-
-            match opcode {
-                OpCode::Add(param1, param2, param3) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = match param3 {
-                        ParamMode::Position => self.get(self.pc + 3),
-                        ParamMode::Immediate => self.get(self.pc + 3),
-                        ParamMode::Relative => self.relbase + self.get(self.pc + 3),
-                    };
-
-                    let value = lhs + rhs;
-                    self.set(dst, value);
-
-                    self.pc += 4;
-                }
-                OpCode::Mul(param1, param2, param3) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = match param3 {
-                        ParamMode::Position => self.get(self.pc + 3),
-                        ParamMode::Immediate => self.get(self.pc + 3),
-                        ParamMode::Relative => self.relbase + self.get(self.pc + 3),
-                    };
-
-                    let value = lhs * rhs;
-                    self.set(dst, value);
-
-                    self.pc += 4;
-                }
-                OpCode::Input(param1) => {
-                    let dst = match param1 {
-                        ParamMode::Position => self.get(self.pc + 1),
-                        ParamMode::Immediate => self.get(self.pc + 1),
-                        ParamMode::Relative => self.relbase + self.get(self.pc + 1),
+        Io::Output(value) => {
+            match frames.push(value) {
+                Some(Frame::Special(_, record)) => score = record[2] as usize,
+                Some(Frame::Record(record)) => {
+                    let pos = (record[0], record[1]);
+                    let c = match tile(record[2]) {
+                        Ok(c) => c,
+                        Err(err) => {
+                            error.get_or_insert(err);
+                            return None;
+                        }
                     };
-
-                    match io(Io::Input) {
-                        Some(value) => self.set(dst, value),
-                        None => return RunStatus::Poll,
+                    if c == '*' {
+                        ball = pos;
+                    } else if c == '-' {
+                        paddle = pos;
                     }
-
-                    self.pc += 2;
-                }
-                OpCode::Output(param1) => {
-                    let src = self.pget(self.pc + 1, param1);
-                    io(Io::Output(src));
-
-                    self.pc += 2;
-                }
-                OpCode::JumpIfTrue(param1, param2) => {
-                    let cnd = self.pget(self.pc + 1, param1);
-                    let val = self.pget(self.pc + 2, param2);
-
-                    self.pc = if cnd != 0 { val } else { self.pc + 3 };
+                    screen.set(pos, c);
                 }
-                OpCode::JumpIfFalse(param1, param2) => {
-                    let cnd = self.pget(self.pc + 1, param1);
-                    let val = self.pget(self.pc + 2, param2);
-
-                    self.pc = if cnd == 0 { val } else { self.pc + 3 };
-                }
-                OpCode::LessThan(param1, param2, param3) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = match param3 {
-                        ParamMode::Position => self.get(self.pc + 3),
-                        ParamMode::Immediate => self.get(self.pc + 3),
-                        ParamMode::Relative => self.relbase + self.get(self.pc + 3),
-                    };
-
-                    let value = if lhs < rhs { 1 } else { 0 };
-                    self.set(dst, value);
-
-                    self.pc += 4;
-                }
-                OpCode::Equals(param1, param2, param3) => {
-                    let lhs = self.pget(self.pc + 1, param1);
-                    let rhs = self.pget(self.pc + 2, param2);
-                    let dst = match param3 {
-                        ParamMode::Position => self.get(self.pc + 3),
-                        ParamMode::Immediate => self.get(self.pc + 3),
-                        ParamMode::Relative => self.relbase + self.get(self.pc + 3),
-                    };
-
-                    let value = if lhs == rhs { 1 } else { 0 };
-                    self.set(dst, value);
-
-                    self.pc += 4;
-                }
-                OpCode::AdjustRelBase(param1) => {
-                    let adj = self.pget(self.pc + 1, param1);
-
-                    self.relbase += adj;
-
-                    self.pc += 2;
-                }
-                OpCode::Eof => return RunStatus::Halt,
+                None => {}
             }
+            None
         }
-    }
-}
+    });
 
-fn _map_to_string(shape: (isize, isize), map: &HashMap<(isize, isize), char>) -> String {
-    let mut out = String::new();
-    for y in 0..shape.0 {
-        for x in 0..shape.1 {
-            let coord = (y, x);
-            out.push(map.get(&coord).copied().unwrap_or(' '));
-        }
-        out.push('\n');
+    if let Some(error) = error {
+        return Err(error);
     }
-    out
+    solution::halted(status)?;
+
+    Ok(score)
 }
 
-pub fn part1(input: &Input) -> usize {
-    let mut map = HashMap::new();
-    let mut pos = (0, 0);
-    let mut shape = (0, 0);
-    let mut counter = 0;
+pub struct Day13;
 
-    let mut tape = input.tape.clone();
-    let status = tape.run(|io| {
-        if let Io::Output(value) = io {
-            let value = value as usize;
-            counter += 1;
-            if counter == 1 {
-                pos.1 = value;
-                shape.1 = shape.1.max(value + 1);
-            } else if counter == 2 {
-                pos.0 = value;
-                shape.0 = shape.0.max(value + 1);
-            } else {
-                let c = match value {
-                    0 => ' ',
-                    1 => '|',
-                    2 => '#',
-                    3 => '-',
-                    4 => '*',
-                    _ => panic!("invalid tile value: {value}"),
-                };
-                map.insert(pos, c);
-                counter = 0;
-            }
-        }
-        None
-    });
+pub const SOLUTION: &dyn Day = &Entry::<Day13>::new();
 
-    assert_eq!(status, RunStatus::Halt);
+impl Solution for Day13 {
+    const DAY: usize = 13;
+    const TITLE: &'static str = "Care Package";
 
-    map.values().filter(|&&tile| tile == '#').count()
-}
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = usize;
 
-pub fn part2(input: &Input) -> usize {
-    let mut map = HashMap::new();
-    let mut pos = (0, 0);
-    let mut shape = (0, 0);
-    let mut counter = 0;
-    let mut ball: (isize, isize) = (0, 0);
-    let mut paddle: (isize, isize) = (0, 0);
-    let mut score = 0;
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
 
-    let mut tape = input.tape.clone();
-    tape.set(0, 2);
-    let status = tape.run(|io| {
-        const SCORE: (isize, isize) = (0, -1);
-        match io {
-            Io::Input => {
-                // println!("{}", _map_to_string(shape, &map));
-                // std::thread::sleep(std::time::Duration::from_millis(16));
-                let signum = (ball.1 - paddle.1).signum();
-                Some(signum)
-            }
-            Io::Output(value) => {
-                counter += 1;
-                if counter == 1 {
-                    pos.1 = value;
-                    shape.1 = shape.1.max(value + 1);
-                } else if counter == 2 {
-                    pos.0 = value;
-                    shape.0 = shape.0.max(value + 1);
-                } else {
-                    if pos == SCORE {
-                        score = value as usize;
-                    } else {
-                        let c = match value {
-                            0 => ' ',
-                            1 => '|',
-                            2 => '#',
-                            3 => '-',
-                            4 => '*',
-                            _ => panic!("invalid tile value: {value}"),
-                        };
-                        if c == '*' {
-                            ball = pos;
-                        } else if c == '-' {
-                            paddle = pos;
-                        }
-                        map.insert(pos, c);
-                    }
-                    counter = 0;
-                }
-                None
-            }
-        }
-    });
+    fn tape(input: &mut Input) -> Option<&mut Tape> {
+        Some(&mut input.tape)
+    }
 
-    assert_eq!(status, RunStatus::Halt);
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        part1(input)
+    }
 
-    score
+    fn part2(input: &Input) -> Result<usize, SolveError> {
+        part2(input)
+    }
 }
@@ -1,55 +1,91 @@
 use std::{
     cmp::Ordering,
     collections::{HashMap, VecDeque},
+    str::FromStr,
 };
 
+use crate::solution::{Day, Entry, Example, ParseError, Solution, SolveError};
+
 pub struct Input {
     reactions: HashMap<String, Reaction>,
 }
 
-impl From<&str> for Input {
-    fn from(value: &str) -> Self {
-        let reactions = value
+impl FromStr for Input {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let reactions = s
             .lines()
             .map(str::trim)
-            .map(Reaction::from)
-            .map(|reaction| (reaction.output.name.clone(), reaction))
-            .collect();
-        Self { reactions }
+            .map(str::parse::<Reaction>)
+            .map(|reaction| reaction.map(|reaction| (reaction.output.name.clone(), reaction)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { reactions })
     }
 }
 
+/// An amount of a chemical, written `7 A`.
 #[derive(Clone, Debug)]
-struct Chemical {
-    name: String,
-    amount: usize,
+pub struct Chemical {
+    pub name: String,
+    pub amount: usize,
 }
 
-impl From<&str> for Chemical {
-    fn from(value: &str) -> Self {
-        let (amount, name) = value.split_once(' ').unwrap();
-        let amount = amount.parse().unwrap();
+impl FromStr for Chemical {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (amount, name) = s
+            .split_once(' ')
+            .ok_or_else(|| ParseError::new(format!("invalid chemical: {s}")))?;
+        let amount = amount.parse()?;
         let name = name.to_string();
-        Self { amount, name }
+        Ok(Self { amount, name })
     }
 }
 
+/// A reaction of the nanofactory, written `7 A, 1 B => 1 C`.
 #[derive(Clone, Debug)]
-struct Reaction {
-    output: Chemical,
-    input: Vec<Chemical>,
+pub struct Reaction {
+    pub output: Chemical,
+    pub input: Vec<Chemical>,
 }
 
-impl From<&str> for Reaction {
-    fn from(value: &str) -> Self {
-        let (input, output) = value.split_once(" => ").unwrap();
-        let output: Chemical = output.into();
-        let input: Vec<_> = input.split(", ").map(Chemical::from).collect();
-        Self { output, input }
+impl FromStr for Reaction {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (input, output) = s
+            .split_once(" => ")
+            .ok_or_else(|| ParseError::new(format!("invalid reaction: {s}")))?;
+        let output: Chemical = output.parse()?;
+        let input = input
+            .split(", ")
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { output, input })
     }
 }
 
-fn calculate(reactions: &HashMap<String, Reaction>, total_fuel: usize) -> usize {
+/// Ore needed to produce `total_fuel` FUEL with `reactions`, keyed by the
+/// chemical they output. Leftovers of a reaction are used up before it runs
+/// again. Panics if a chemical has no reaction producing it.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use advent_of_code_2019::day14::{calculate, Reaction};
+///
+/// let lines = ["10 ORE => 10 A", "1 ORE => 1 B", "7 A, 1 B => 1 FUEL"];
+/// let reactions: HashMap<String, Reaction> = lines
+///     .iter()
+///     .map(|line| line.parse::<Reaction>().unwrap())
+///     .map(|reaction| (reaction.output.name.clone(), reaction))
+///     .collect();
+/// assert_eq!(calculate(&reactions, 1), 11);
+/// assert_eq!(calculate(&reactions, 2), 22);
+/// ```
+pub fn calculate(reactions: &HashMap<String, Reaction>, total_fuel: usize) -> usize {
     let mut total_ore = 0;
     let fuel = Chemical {
         name: "FUEL".into(),
@@ -103,6 +139,36 @@ pub fn part2(input: &Input) -> usize {
     low - 1
 }
 
+pub struct Day14;
+
+pub const SOLUTION: &dyn Day = &Entry::<Day14>::new();
+
+impl Solution for Day14 {
+    const DAY: usize = 14;
+    const TITLE: &'static str = "Space Stoichiometry";
+    const EXAMPLES: &'static [Example] = &[Example {
+        input: "10 ORE => 10 A\n1 ORE => 1 B\n7 A, 1 B => 1 C\n7 A, 1 C => 1 D\n7 A, 1 D => 1 E\n7 A, 1 E => 1 FUEL\n",
+        part1: Some("31"),
+        part2: None,
+    }];
+
+    type Input = Input;
+    type Part1 = usize;
+    type Part2 = usize;
+
+    fn parse(input: &str) -> Result<Input, ParseError> {
+        input.parse()
+    }
+
+    fn part1(input: &Input) -> Result<usize, SolveError> {
+        Ok(part1(input))
+    }
+
+    fn part2(input: &Input) -> Result<usize, SolveError> {
+        Ok(part2(input))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -116,7 +182,7 @@ mod test {
         7 A, 1 D => 1 E
         7 A, 1 E => 1 FUEL"#;
 
-        assert_eq!(part1(&INPUT.into()), 31);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 31);
     }
 
     #[test]
@@ -129,7 +195,7 @@ mod test {
         4 C, 1 A => 1 CA
         2 AB, 3 BC, 4 CA => 1 FUEL"#;
 
-        assert_eq!(part1(&INPUT.into()), 165);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 165);
     }
 
     #[test]
@@ -144,7 +210,7 @@ mod test {
         165 ORE => 2 GPVTF
         3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT"#;
 
-        assert_eq!(part1(&INPUT.into()), 13312);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 13312);
     }
 
     #[test]
@@ -162,7 +228,7 @@ mod test {
         1 VJHF, 6 MNCFX => 4 RFSQX
         176 ORE => 6 VJHF"#;
 
-        assert_eq!(part1(&INPUT.into()), 180697);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 180697);
     }
 
     #[test]
@@ -185,7 +251,7 @@ mod test {
         7 XCVML => 6 RJRHP
         5 BHXH, 4 VRPVC => 5 LTCX"#;
 
-        assert_eq!(part1(&INPUT.into()), 2210736);
+        assert_eq!(part1(&INPUT.parse().unwrap()), 2210736);
     }
 
     #[test]
@@ -200,7 +266,7 @@ mod test {
         165 ORE => 2 GPVTF
         3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT"#;
 
-        assert_eq!(part2(&INPUT.into()), 82892753);
+        assert_eq!(part2(&INPUT.parse().unwrap()), 82892753);
     }
 
     #[test]
@@ -218,7 +284,7 @@ mod test {
         1 VJHF, 6 MNCFX => 4 RFSQX
         176 ORE => 6 VJHF"#;
 
-        assert_eq!(part2(&INPUT.into()), 5586022);
+        assert_eq!(part2(&INPUT.parse().unwrap()), 5586022);
     }
 
     #[test]
@@ -241,6 +307,6 @@ mod test {
         7 XCVML => 6 RJRHP
         5 BHXH, 4 VRPVC => 5 LTCX"#;
 
-        assert_eq!(part2(&INPUT.into()), 460664);
+        assert_eq!(part2(&INPUT.parse().unwrap()), 460664);
     }
 }
@@ -0,0 +1,97 @@
+use std::fs;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// Environment variable overriding the `input` directory.
+pub const INPUT_DIR_VAR: &str = "AOC_INPUT_DIR";
+
+pub const ANSWERS_FILE: &str = "answers.toml";
+
+/// Directory holding `dayNN.txt` files, and one subdirectory per user with
+/// more of them.
+pub fn input_dir() -> PathBuf {
+    std::env::var_os(INPUT_DIR_VAR).map_or_else(|| PathBuf::from("input"), PathBuf::from)
+}
+
+/// Where a day's puzzle input is read from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Input {
+    /// `dayNN.txt` in the input directory.
+    #[default]
+    Dir,
+    /// `dayNN.txt` in a user's subdirectory of the input directory.
+    User(String),
+    /// Every user's subdirectory in turn.
+    AllUsers,
+    File(PathBuf),
+    Stdin,
+}
+
+impl Input {
+    /// The inputs to run one after the other, with `AllUsers` expanded into
+    /// one `User` per subdirectory of the input directory.
+    pub fn expand(&self) -> Result<Vec<Input>, Error> {
+        if *self != Input::AllUsers {
+            return Ok(vec![self.clone()]);
+        }
+        let dir = input_dir();
+        let mut users = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                users.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        if users.is_empty() {
+            return Err(Error::Logic(format!(
+                "no user directories in {}",
+                dir.display()
+            )));
+        }
+        users.sort();
+        Ok(users.into_iter().map(Input::User).collect())
+    }
+
+    pub fn user(&self) -> Option<&str> {
+        match self {
+            Input::User(user) => Some(user),
+            _ => None,
+        }
+    }
+
+    /// Expected answers for this input: `answers.toml` beside the `dayNN.txt`
+    /// files, or `<name>.answers.toml` beside a single input file.
+    pub fn answers_path(&self) -> Option<PathBuf> {
+        match self {
+            Input::Dir => Some(input_dir().join(ANSWERS_FILE)),
+            Input::User(user) => Some(input_dir().join(user).join(ANSWERS_FILE)),
+            Input::File(path) => Some(path.with_extension(ANSWERS_FILE)),
+            Input::AllUsers | Input::Stdin => None,
+        }
+    }
+
+    pub fn read(&self, day: usize) -> Result<String, Error> {
+        let filename = format!("day{day:02}.txt");
+        let content = match self {
+            Input::Dir => read_file(&input_dir().join(filename))?,
+            Input::User(user) => read_file(&input_dir().join(user).join(filename))?,
+            Input::AllUsers => unreachable!("expand AllUsers before reading"),
+            Input::File(path) => read_file(path)?,
+            Input::Stdin => {
+                let mut content = String::new();
+                io::stdin().read_to_string(&mut content)?;
+                content
+            }
+        };
+        Ok(content)
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => Error::MissingInput(path.display().to_string()),
+        _ => Error::Io(err),
+    })
+}
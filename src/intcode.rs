@@ -0,0 +1,717 @@
+// Intcode computer shared by the Intcode puzzles. The full instruction set is
+// described in https://adventofcode.com/2019/day/9.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use bus::{Bus, BusError, Device, Shared};
+use disasm::Instruction;
+use heatmap::Heatmap;
+use transcript::Recorder;
+
+pub mod bus;
+pub mod cfg;
+pub mod compiler;
+pub mod decompile;
+pub mod disasm;
+pub mod frame;
+pub mod heatmap;
+pub mod lint;
+pub mod optimize;
+pub mod patch;
+pub mod search;
+pub mod transcript;
+
+pub type Integer = isize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParamMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl ParamMode {
+    pub fn decode(value: Integer) -> Option<Self> {
+        match value {
+            0 => Some(ParamMode::Position),
+            1 => Some(ParamMode::Immediate),
+            2 => Some(ParamMode::Relative),
+            _ => None,
+        }
+    }
+}
+
+impl From<Integer> for ParamMode {
+    fn from(value: Integer) -> Self {
+        ParamMode::decode(value).unwrap_or_else(|| panic!("invalid ParamMode value: {value}"))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OpCode {
+    Add(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs + rhs
+    Mul(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : dst = lhs * rhs
+    Input(ParamMode),                     // <op>,<dst>             : dst = *input*
+    Output(ParamMode),                    // <op>,<src>             : *output* = src
+    JumpIfTrue(ParamMode, ParamMode),     // <op>,<cnd>,<val>       : if cnd != 0 then pc = val
+    JumpIfFalse(ParamMode, ParamMode),    // <op>,<cnd>,<val>       : if cnd == 0 then pc = val
+    LessThan(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs < rhs then dst = 1 else dst = 0
+    Equals(ParamMode, ParamMode, ParamMode), // <op>,<lhs>,<rhs>,<dst> : if lhs == rhs then dst = 1 else dst = 0
+    AdjustRelBase(ParamMode),                // <op>,<adj>              : relbase += adj
+    Eof,
+}
+
+impl OpCode {
+    pub fn decode(value: Integer) -> Option<Self> {
+        let param3 = ParamMode::decode((value / 10000) % 10)?;
+        let param2 = ParamMode::decode((value / 1000) % 10)?;
+        let param1 = ParamMode::decode((value / 100) % 10)?;
+        let opcode = value % 100;
+        match opcode {
+            1 => Some(OpCode::Add(param1, param2, param3)),
+            2 => Some(OpCode::Mul(param1, param2, param3)),
+            3 => Some(OpCode::Input(param1)),
+            4 => Some(OpCode::Output(param1)),
+            5 => Some(OpCode::JumpIfTrue(param1, param2)),
+            6 => Some(OpCode::JumpIfFalse(param1, param2)),
+            7 => Some(OpCode::LessThan(param1, param2, param3)),
+            8 => Some(OpCode::Equals(param1, param2, param3)),
+            9 => Some(OpCode::AdjustRelBase(param1)),
+            99 => Some(OpCode::Eof),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            OpCode::Add(..) => "add",
+            OpCode::Mul(..) => "mul",
+            OpCode::Input(..) => "in",
+            OpCode::Output(..) => "out",
+            OpCode::JumpIfTrue(..) => "jnz",
+            OpCode::JumpIfFalse(..) => "jz",
+            OpCode::LessThan(..) => "lt",
+            OpCode::Equals(..) => "eq",
+            OpCode::AdjustRelBase(..) => "arb",
+            OpCode::Eof => "halt",
+        }
+    }
+
+    pub fn modes(&self) -> Vec<ParamMode> {
+        match *self {
+            OpCode::Add(p1, p2, p3)
+            | OpCode::Mul(p1, p2, p3)
+            | OpCode::LessThan(p1, p2, p3)
+            | OpCode::Equals(p1, p2, p3) => vec![p1, p2, p3],
+            OpCode::JumpIfTrue(p1, p2) | OpCode::JumpIfFalse(p1, p2) => vec![p1, p2],
+            OpCode::Input(p1) | OpCode::Output(p1) | OpCode::AdjustRelBase(p1) => vec![p1],
+            OpCode::Eof => vec![],
+        }
+    }
+
+    /// Index of the parameter the instruction writes to, if any.
+    pub fn dst_index(&self) -> Option<usize> {
+        match self {
+            OpCode::Add(..) | OpCode::Mul(..) | OpCode::LessThan(..) | OpCode::Equals(..) => {
+                Some(2)
+            }
+            OpCode::Input(..) => Some(0),
+            _ => None,
+        }
+    }
+}
+
+impl From<Integer> for OpCode {
+    fn from(value: Integer) -> Self {
+        OpCode::decode(value).unwrap_or_else(|| panic!("invalid OpCode value: {value}"))
+    }
+}
+
+/// Why `run` returned. `outputs` counts the values produced during that call
+/// which the caller has been handed but may not have consumed yet.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RunStatus {
+    /// Waiting for input at the `in` instruction at `pc`.
+    Poll { pc: Integer, outputs: usize },
+    /// About to execute the instruction at `pc`, which has breakpoint `id`.
+    Breakpoint {
+        id: usize,
+        pc: Integer,
+        outputs: usize,
+    },
+    /// The step budget ran out before the instruction at `pc`.
+    StepLimit { pc: Integer, outputs: usize },
+    /// Reached `halt`. The exit code is the last value output in this call.
+    Halt {
+        exit_code: Option<Integer>,
+        outputs: usize,
+    },
+}
+
+impl RunStatus {
+    pub fn is_poll(&self) -> bool {
+        matches!(self, RunStatus::Poll { .. })
+    }
+
+    pub fn is_halt(&self) -> bool {
+        matches!(self, RunStatus::Halt { .. })
+    }
+
+    pub fn outputs(&self) -> usize {
+        match *self {
+            RunStatus::Poll { outputs, .. }
+            | RunStatus::Breakpoint { outputs, .. }
+            | RunStatus::StepLimit { outputs, .. }
+            | RunStatus::Halt { outputs, .. } => outputs,
+        }
+    }
+}
+
+pub enum Io {
+    Input,
+    Output(Integer),
+}
+
+pub const PAGE_SIZE: usize = 256;
+
+type Page = [Integer; PAGE_SIZE];
+
+/// Paged memory where clones share every page until one of them writes to it.
+/// Pages that were never written are not allocated and read as zero.
+#[derive(Clone, Debug, Default)]
+pub struct Memory {
+    pages: Vec<Option<Arc<Page>>>,
+    len: usize,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MemoryStats {
+    pub pages: usize,
+    pub shared_pages: usize,
+    pub private_pages: usize,
+}
+
+impl MemoryStats {
+    pub fn shared_ratio(&self) -> f64 {
+        if self.pages == 0 {
+            0.0
+        } else {
+            self.shared_pages as f64 / self.pages as f64
+        }
+    }
+}
+
+impl From<Vec<Integer>> for Memory {
+    fn from(value: Vec<Integer>) -> Self {
+        let len = value.len();
+        let pages = value
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Some(Arc::new(page))
+            })
+            .collect();
+        Self { pages, len }
+    }
+}
+
+impl Memory {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, pos: usize) -> Integer {
+        let (page, offset) = (pos / PAGE_SIZE, pos % PAGE_SIZE);
+        match self.pages.get(page) {
+            Some(Some(page)) => page[offset],
+            _ => 0,
+        }
+    }
+
+    pub fn set(&mut self, pos: usize, value: Integer) {
+        let (page, offset) = (pos / PAGE_SIZE, pos % PAGE_SIZE);
+        if page >= self.pages.len() {
+            self.pages.resize(page + 1, None);
+        }
+        let page = self.pages[page].get_or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+        Arc::make_mut(page)[offset] = value;
+        self.len = self.len.max(pos + 1);
+    }
+
+    pub fn to_vec(&self) -> Vec<Integer> {
+        (0..self.len).map(|pos| self.get(pos)).collect()
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        for page in self.pages.iter().flatten() {
+            stats.pages += 1;
+            if Arc::strong_count(page) > 1 {
+                stats.shared_pages += 1;
+            } else {
+                stats.private_pages += 1;
+            }
+        }
+        stats
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Tape {
+    mem: Memory,
+    pc: Integer,
+    relbase: Integer,
+    heatmap: Option<Heatmap>,
+    bus: Bus,
+    breakpoints: Vec<Option<Integer>>,
+    resume: Option<Integer>,
+    patched: Vec<Integer>,
+    recorder: Option<Recorder>,
+}
+
+impl FromStr for Tape {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mem: Vec<Integer> = s
+            .trim()
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::from(mem))
+    }
+}
+
+impl From<Vec<Integer>> for Tape {
+    fn from(value: Vec<Integer>) -> Self {
+        Self {
+            mem: value.into(),
+            pc: 0,
+            relbase: 0,
+            heatmap: None,
+            bus: Bus::default(),
+            breakpoints: Vec::new(),
+            resume: None,
+            patched: Vec::new(),
+            recorder: None,
+        }
+    }
+}
+
+impl Tape {
+    /// Creates a machine in the same state. Memory pages are shared with
+    /// `self` and only copied once either side writes to them, so memory is
+    /// independent. Attached devices and the transcript recorder are not:
+    /// both machines keep talking to the same ones.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn empty(&self) -> bool {
+        self.mem.is_empty()
+    }
+
+    pub fn halted(&self) -> bool {
+        let opcode: OpCode = self.get(self.pc).into();
+        opcode == OpCode::Eof
+    }
+
+    pub fn pc(&self) -> Integer {
+        self.pc
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.mem
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.mem.stats()
+    }
+
+    /// Starts counting reads, writes and executions per address in `run`.
+    pub fn record_heatmap(&mut self) {
+        self.heatmap.get_or_insert_with(Heatmap::default);
+    }
+
+    pub fn heatmap(&self) -> Option<&Heatmap> {
+        self.heatmap.as_ref()
+    }
+
+    /// Sends the machine's inputs and outputs through `recorder`, and those of
+    /// every machine forked from it from now on.
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    /// Makes `run` stop before executing the instruction at `pc`. Running
+    /// again resumes past the breakpoint.
+    pub fn add_breakpoint(&mut self, pc: Integer) -> usize {
+        self.breakpoints.push(Some(pc));
+        self.breakpoints.len() - 1
+    }
+
+    pub fn remove_breakpoint(&mut self, id: usize) {
+        if let Some(breakpoint) = self.breakpoints.get_mut(id) {
+            *breakpoint = None;
+        }
+    }
+
+    fn breakpoint(&self) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .position(|&breakpoint| breakpoint == Some(self.pc))
+    }
+
+    fn trace(&mut self) {
+        if let Some(mut heatmap) = self.heatmap.take() {
+            if let Some(instruction) = Instruction::decode(self, self.pc) {
+                heatmap.record(&instruction, self.relbase);
+            }
+            self.heatmap = Some(heatmap);
+        }
+    }
+
+    pub fn get(&self, pos: Integer) -> Integer {
+        assert!(pos >= 0, "invalid get access: {pos}");
+        self.mem.get(pos as usize)
+    }
+
+    pub fn set(&mut self, pos: Integer, value: Integer) {
+        assert!(pos >= 0, "invalid set access: {pos}");
+        self.mem.set(pos as usize, value);
+    }
+
+    /// Writes `value` to `pos` unless a patch has set it. Days make their own
+    /// edits to the program this way, so that patches take precedence.
+    pub fn set_unpatched(&mut self, pos: Integer, value: Integer) {
+        if !self.patched.contains(&pos) {
+            self.set(pos, value);
+        }
+    }
+
+    /// Maps `device` at `range`. The machine's data reads and writes there
+    /// go to the device, instruction fetches and `get`/`set` do not.
+    pub fn attach<D: Device + 'static>(
+        &mut self,
+        range: std::ops::Range<usize>,
+        device: Shared<D>,
+    ) -> Result<(), BusError> {
+        self.bus.attach(range, device)
+    }
+
+    fn load(&self, pos: Integer) -> Integer {
+        match self.bus.read(pos) {
+            Some(value) => value,
+            None => self.get(pos),
+        }
+    }
+
+    fn store(&mut self, pos: Integer, value: Integer) {
+        if !self.bus.write(pos, value) {
+            self.set(pos, value);
+        }
+    }
+
+    fn pget(&self, pos: Integer, param: ParamMode) -> Integer {
+        match param {
+            ParamMode::Position => self.load(self.get(pos)),
+            ParamMode::Immediate => self.get(pos),
+            ParamMode::Relative => self.load(self.relbase + self.get(pos)),
+        }
+    }
+
+    fn dst(&self, pos: Integer, param: ParamMode) -> Integer {
+        match param {
+            ParamMode::Position => self.get(pos),
+            ParamMode::Immediate => self.get(pos),
+            ParamMode::Relative => self.relbase + self.get(pos),
+        }
+    }
+
+    pub fn run<F>(&mut self, io: F) -> RunStatus
+    where
+        F: FnMut(Io) -> Option<Integer>,
+    {
+        self.execute(None, io)
+    }
+
+    /// Like `run`, but executes at most `steps` instructions.
+    pub fn run_limited<F>(&mut self, steps: usize, io: F) -> RunStatus
+    where
+        F: FnMut(Io) -> Option<Integer>,
+    {
+        self.execute(Some(steps), io)
+    }
+
+    fn execute<F>(&mut self, mut steps: Option<usize>, mut io: F) -> RunStatus
+    where
+        F: FnMut(Io) -> Option<Integer>,
+    {
+        let mut outputs = 0;
+        let mut exit_code = None;
+        if self.empty() {
+            return RunStatus::Halt { exit_code, outputs };
+        }
+
+        loop {
+            if let Some(id) = self.breakpoint() {
+                if self.resume.take() != Some(self.pc) {
+                    self.resume = Some(self.pc);
+                    let pc = self.pc;
+                    return RunStatus::Breakpoint { id, pc, outputs };
+                }
+            }
+            match &mut steps {
+                Some(0) => {
+                    let pc = self.pc;
+                    return RunStatus::StepLimit { pc, outputs };
+                }
+                Some(steps) => *steps -= 1,
+                None => {}
+            }
+
+            if !self.bus.is_empty() {
+                self.bus.tick();
+            }
+
+            let opcode: OpCode = self.get(self.pc).into();
+
+            if !matches!(opcode, OpCode::Input(_)) {
+                self.trace();
+            }
+
+            match opcode {
+                OpCode::Add(param1, param2, param3) => {
+                    let lhs = self.pget(self.pc + 1, param1);
+                    let rhs = self.pget(self.pc + 2, param2);
+                    let dst = self.dst(self.pc + 3, param3);
+
+                    let value = lhs + rhs;
+                    self.store(dst, value);
+
+                    self.pc += 4;
+                }
+                OpCode::Mul(param1, param2, param3) => {
+                    let lhs = self.pget(self.pc + 1, param1);
+                    let rhs = self.pget(self.pc + 2, param2);
+                    let dst = self.dst(self.pc + 3, param3);
+
+                    let value = lhs * rhs;
+                    self.store(dst, value);
+
+                    self.pc += 4;
+                }
+                OpCode::Input(param1) => {
+                    let dst = self.dst(self.pc + 1, param1);
+
+                    let value = match &self.recorder {
+                        Some(recorder) => recorder.input(|| io(Io::Input)),
+                        None => io(Io::Input),
+                    };
+                    match value {
+                        Some(value) => {
+                            self.trace();
+                            self.store(dst, value);
+                        }
+                        None => {
+                            let pc = self.pc;
+                            return RunStatus::Poll { pc, outputs };
+                        }
+                    }
+
+                    self.pc += 2;
+                }
+                OpCode::Output(param1) => {
+                    let src = self.pget(self.pc + 1, param1);
+                    if let Some(recorder) = &self.recorder {
+                        recorder.output(src);
+                    }
+                    io(Io::Output(src));
+                    outputs += 1;
+                    exit_code = Some(src);
+
+                    self.pc += 2;
+                }
+                OpCode::JumpIfTrue(param1, param2) => {
+                    let cnd = self.pget(self.pc + 1, param1);
+                    let val = self.pget(self.pc + 2, param2);
+
+                    self.pc = if cnd != 0 { val } else { self.pc + 3 };
+                }
+                OpCode::JumpIfFalse(param1, param2) => {
+                    let cnd = self.pget(self.pc + 1, param1);
+                    let val = self.pget(self.pc + 2, param2);
+
+                    self.pc = if cnd == 0 { val } else { self.pc + 3 };
+                }
+                OpCode::LessThan(param1, param2, param3) => {
+                    let lhs = self.pget(self.pc + 1, param1);
+                    let rhs = self.pget(self.pc + 2, param2);
+                    let dst = self.dst(self.pc + 3, param3);
+
+                    let value = if lhs < rhs { 1 } else { 0 };
+                    self.store(dst, value);
+
+                    self.pc += 4;
+                }
+                OpCode::Equals(param1, param2, param3) => {
+                    let lhs = self.pget(self.pc + 1, param1);
+                    let rhs = self.pget(self.pc + 2, param2);
+                    let dst = self.dst(self.pc + 3, param3);
+
+                    let value = if lhs == rhs { 1 } else { 0 };
+                    self.store(dst, value);
+
+                    self.pc += 4;
+                }
+                OpCode::AdjustRelBase(param1) => {
+                    let adj = self.pget(self.pc + 1, param1);
+
+                    self.relbase += adj;
+
+                    self.pc += 2;
+                }
+                OpCode::Eof => return RunStatus::Halt { exit_code, outputs },
+            }
+        }
+    }
+
+    /// Runs with a fixed list of inputs and collects every output produced
+    /// until the machine halts or polls for more input.
+    pub fn run_with(&mut self, input: &[Integer]) -> (RunStatus, Vec<Integer>) {
+        let mut input = input.iter().copied();
+        let mut output = Vec::new();
+        let status = self.run(|io| match io {
+            Io::Input => input.next(),
+            Io::Output(value) => {
+                output.push(value);
+                None
+            }
+        });
+        (status, output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_tape(tape_str: &str, input: &[Integer]) -> Vec<Integer> {
+        let mut tape = Tape::from_str(tape_str).unwrap();
+        let (status, output) = tape.run_with(input);
+        assert!(status.is_halt());
+        output
+    }
+
+    #[test]
+    fn test_quine() {
+        const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        assert_eq!(
+            test_tape(QUINE, &[]),
+            vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        const TAPE: &str = "3,3,1107,-1,8,3,4,3,99";
+        assert_eq!(test_tape(TAPE, &[7]), vec![1]);
+        assert_eq!(test_tape(TAPE, &[8]), vec![0]);
+    }
+
+    #[test]
+    fn test_poll() {
+        let mut tape = Tape::from_str("3,9,4,9,3,9,4,9,99,0").unwrap();
+        assert_eq!(
+            tape.run_with(&[5]),
+            (RunStatus::Poll { pc: 4, outputs: 1 }, vec![5])
+        );
+        assert_eq!(
+            tape.run_with(&[6]),
+            (
+                RunStatus::Halt {
+                    exit_code: Some(6),
+                    outputs: 1
+                },
+                vec![6]
+            )
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_and_step_limit() {
+        // Counts down from 3, printing every value.
+        let mut tape = Tape::from_str("4,12,1001,12,-1,12,1005,12,0,99,0,0,3").unwrap();
+        let id = tape.add_breakpoint(6);
+
+        let run = |tape: &mut Tape| tape.run_with(&[]).0;
+        assert_eq!(
+            run(&mut tape),
+            RunStatus::Breakpoint {
+                id,
+                pc: 6,
+                outputs: 1
+            }
+        );
+        assert_eq!(
+            run(&mut tape),
+            RunStatus::Breakpoint {
+                id,
+                pc: 6,
+                outputs: 1
+            }
+        );
+
+        tape.remove_breakpoint(id);
+        let status = tape.run_limited(3, |_| None);
+        assert_eq!(status, RunStatus::StepLimit { pc: 6, outputs: 1 });
+        assert_eq!(
+            run(&mut tape),
+            RunStatus::Halt {
+                exit_code: None,
+                outputs: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_fork_shares_pages() {
+        let mem: Vec<Integer> = (0..4 * PAGE_SIZE as Integer).collect();
+        let tape = Tape::from(mem);
+        let mut fork = tape.fork();
+
+        let stats = fork.memory_stats();
+        assert_eq!(stats.pages, 4);
+        assert_eq!(stats.shared_pages, 4);
+
+        fork.set(PAGE_SIZE as Integer + 1, -1);
+
+        let stats = fork.memory_stats();
+        assert_eq!(stats.shared_pages, 3);
+        assert_eq!(stats.private_pages, 1);
+        assert_eq!(tape.get(PAGE_SIZE as Integer + 1), PAGE_SIZE as Integer + 1);
+        assert_eq!(fork.get(PAGE_SIZE as Integer + 1), -1);
+    }
+
+    #[test]
+    fn test_sparse_memory() {
+        let mut tape = Tape::from_str("99").unwrap();
+        assert_eq!(tape.get(1_000_000), 0);
+        assert_eq!(tape.memory_stats().pages, 1);
+
+        tape.set(1_000_000, 7);
+        assert_eq!(tape.get(1_000_000), 7);
+        assert_eq!(tape.memory_stats().pages, 2);
+        assert_eq!(tape.memory().len(), 1_000_001);
+    }
+}
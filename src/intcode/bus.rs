@@ -0,0 +1,287 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use super::frame::Screen;
+use super::Integer;
+
+/// Hardware mapped into a machine's address space. Offsets are relative to
+/// the start of the range the device is attached at.
+pub trait Device: Send {
+    fn read(&mut self, offset: usize) -> Integer;
+
+    fn write(&mut self, offset: usize, value: Integer);
+
+    /// Called before every instruction the machine executes.
+    fn tick(&mut self) {}
+}
+
+/// A device the caller keeps a handle on, so its state can be inspected
+/// after the machine ran.
+pub type Shared<D> = Arc<Mutex<D>>;
+
+pub fn shared<D: Device>(device: D) -> Shared<D> {
+    Arc::new(Mutex::new(device))
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum BusError {
+    #[error("empty address range {0:?}")]
+    Empty(Range<usize>),
+    #[error("address range {0:?} overlaps {1:?}")]
+    Overlap(Range<usize>, Range<usize>),
+}
+
+type Mapping = (Range<usize>, Arc<Mutex<dyn Device>>);
+
+/// Routes data reads and writes in mapped ranges to devices. Forks of a
+/// machine share its devices.
+#[derive(Clone, Default)]
+pub struct Bus {
+    mappings: Vec<Mapping>,
+}
+
+impl fmt::Debug for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.mappings.iter().map(|(range, _)| range))
+            .finish()
+    }
+}
+
+impl Bus {
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    pub fn attach<D: Device + 'static>(
+        &mut self,
+        range: Range<usize>,
+        device: Shared<D>,
+    ) -> Result<(), BusError> {
+        if range.is_empty() {
+            return Err(BusError::Empty(range));
+        }
+        if let Some((other, _)) = self
+            .mappings
+            .iter()
+            .find(|(other, _)| other.start < range.end && range.start < other.end)
+        {
+            return Err(BusError::Overlap(range, other.clone()));
+        }
+        self.mappings.push((range, device));
+        Ok(())
+    }
+
+    fn device(&self, pos: Integer) -> Option<(usize, &Arc<Mutex<dyn Device>>)> {
+        let pos = usize::try_from(pos).ok()?;
+        self.mappings
+            .iter()
+            .find(|(range, _)| range.contains(&pos))
+            .map(|(range, device)| (pos - range.start, device))
+    }
+
+    /// Value of a mapped cell, or `None` if `pos` is plain memory.
+    pub fn read(&self, pos: Integer) -> Option<Integer> {
+        let (offset, device) = self.device(pos)?;
+        Some(device.lock().unwrap().read(offset))
+    }
+
+    /// Whether the write went to a device.
+    pub fn write(&self, pos: Integer, value: Integer) -> bool {
+        match self.device(pos) {
+            Some((offset, device)) => {
+                device.lock().unwrap().write(offset, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn tick(&self) {
+        for (_, device) in &self.mappings {
+            device.lock().unwrap().tick();
+        }
+    }
+}
+
+/// Character terminal. Writing a cell prints it as ASCII, reading one takes
+/// the next queued character, or -1 once the queue is empty.
+#[derive(Clone, Debug, Default)]
+pub struct Console {
+    pub input: VecDeque<Integer>,
+    pub output: String,
+}
+
+impl Console {
+    pub fn feed(&mut self, text: &str) {
+        self.input.extend(text.bytes().map(Integer::from));
+    }
+}
+
+impl Device for Console {
+    fn read(&mut self, _offset: usize) -> Integer {
+        self.input.pop_front().unwrap_or(-1)
+    }
+
+    fn write(&mut self, _offset: usize, value: Integer) {
+        self.output
+            .push(u8::try_from(value).map_or('?', char::from));
+    }
+}
+
+/// Counts executed instructions. Writing sets the counter.
+#[derive(Clone, Debug, Default)]
+pub struct Clock {
+    pub cycles: Integer,
+}
+
+impl Device for Clock {
+    fn read(&mut self, _offset: usize) -> Integer {
+        self.cycles
+    }
+
+    fn write(&mut self, _offset: usize, value: Integer) {
+        self.cycles = value;
+    }
+
+    fn tick(&mut self) {
+        self.cycles += 1;
+    }
+}
+
+/// Xorshift generator producing non-negative values. Writing reseeds it.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero.
+        Self { state: seed.max(1) }
+    }
+}
+
+impl Device for Random {
+    fn read(&mut self, _offset: usize) -> Integer {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 2) as Integer
+    }
+
+    fn write(&mut self, _offset: usize, value: Integer) {
+        *self = Self::new(value as u64);
+    }
+}
+
+/// Row-major pixel buffer `width` cells wide.
+#[derive(Clone, Debug)]
+pub struct Framebuffer {
+    width: NonZeroUsize,
+    pub screen: Screen<Integer>,
+}
+
+impl Framebuffer {
+    pub fn new(width: NonZeroUsize) -> Self {
+        Self {
+            width,
+            screen: Screen::default(),
+        }
+    }
+
+    fn pos(&self, offset: usize) -> (Integer, Integer) {
+        let width = self.width.get();
+        ((offset % width) as Integer, (offset / width) as Integer)
+    }
+}
+
+impl Device for Framebuffer {
+    fn read(&mut self, offset: usize) -> Integer {
+        self.screen.get(self.pos(offset)).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, offset: usize, value: Integer) {
+        self.screen.set(self.pos(offset), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::intcode::Tape;
+
+    #[test]
+    fn test_console_and_framebuffer() {
+        // Echoes console input until it runs dry, then draws a diagonal
+        // into a 2x2 framebuffer.
+        const TAPE: &str = "1001,1000,0,30,1007,30,0,31,1005,31,18,1001,30,0,1001,1105,1,0,\
+                            1101,1,0,1002,1101,1,0,1005,99,0,0,0,0,0";
+        let console = shared(Console::default());
+        let framebuffer = shared(Framebuffer::new(NonZeroUsize::new(2).unwrap()));
+        let mut tape = Tape::from_str(TAPE).unwrap();
+        tape.attach(1000..1002, console.clone()).unwrap();
+        tape.attach(1002..1006, framebuffer.clone()).unwrap();
+        console.lock().unwrap().feed("hi");
+
+        let (status, _) = tape.run_with(&[]);
+        assert!(status.is_halt());
+
+        assert_eq!(console.lock().unwrap().output, "hi");
+        let framebuffer = framebuffer.lock().unwrap();
+        assert_eq!(
+            framebuffer
+                .screen
+                .render(|pixel| if pixel == Some(&1) { '#' } else { '.' }),
+            "#.\n.#\n"
+        );
+    }
+
+    #[test]
+    fn test_clock_and_random() {
+        // Reads the clock at 500 and a random value at 501 into 12 and 13.
+        const TAPE: &str = "1001,500,0,12,1001,501,0,13,99,0,0,0,0,0";
+        let clock = shared(Clock::default());
+        let mut tape = Tape::from_str(TAPE).unwrap();
+        tape.attach(500..501, clock.clone()).unwrap();
+        tape.attach(501..502, shared(Random::new(7))).unwrap();
+
+        tape.run_with(&[]);
+        assert_eq!(tape.get(12), 1);
+        assert_eq!(tape.get(13), Random::new(7).read(0));
+        assert_eq!(clock.lock().unwrap().cycles, 3);
+
+        assert_eq!(
+            tape.attach(400..501, shared(Clock::default())),
+            Err(BusError::Overlap(400..501, 500..501))
+        );
+        assert_eq!(
+            tape.attach(10..10, shared(Clock::default())),
+            Err(BusError::Empty(10..10))
+        );
+    }
+
+    #[test]
+    fn test_fork_shares_devices() {
+        // Prints 'H' on the console at 500.
+        const TAPE: &str = "1101,72,0,500,99";
+        let console = shared(Console::default());
+        let mut tape = Tape::from_str(TAPE).unwrap();
+        tape.attach(500..501, console.clone()).unwrap();
+
+        let mut fork = tape.fork();
+        fork.run_with(&[]);
+        assert_eq!(tape.pc(), 0);
+        assert_eq!(console.lock().unwrap().output, "H");
+
+        // The original prints to the same console.
+        tape.run_with(&[]);
+        assert_eq!(console.lock().unwrap().output, "HH");
+    }
+}
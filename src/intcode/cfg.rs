@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::disasm::Instruction;
+use super::{Integer, OpCode, ParamMode, Tape};
+
+/// Value an instruction stores if it only depends on immediate operands,
+/// which is how call sites push their return address. `None` as well if the
+/// result overflows.
+pub fn constant_store(instruction: &Instruction) -> Option<Integer> {
+    let params = &instruction.params;
+    match instruction.opcode {
+        OpCode::Add(ParamMode::Immediate, ParamMode::Immediate, _) => {
+            params[0].value.checked_add(params[1].value)
+        }
+        OpCode::Mul(ParamMode::Immediate, ParamMode::Immediate, _) => {
+            params[0].value.checked_mul(params[1].value)
+        }
+        _ => None,
+    }
+}
+
+/// Instructions reachable from address 0, following fall-through and
+/// immediate jump targets. Jumps through memory cannot be followed
+/// statically, so when one is reachable the constants stored by reachable
+/// code are tried as entry points as well.
+pub fn reachable(tape: &Tape) -> (BTreeMap<Integer, Instruction>, Vec<Integer>) {
+    let len = tape.memory().len() as Integer;
+    let mut instructions = BTreeMap::new();
+    let mut invalid = Vec::new();
+    let mut visited = HashSet::new();
+    let mut worklist = vec![0];
+    let mut dynamic_jump = false;
+
+    loop {
+        while let Some(addr) = worklist.pop() {
+            // Out-of-range addresses are marked as visited too, otherwise
+            // constant stores pointing outside the tape are retried forever.
+            if !visited.insert(addr) || !(0..len).contains(&addr) {
+                continue;
+            }
+            let Some(instruction) = Instruction::decode(tape, addr) else {
+                invalid.push(addr);
+                continue;
+            };
+            if instruction.falls_through() {
+                worklist.push(instruction.next());
+            }
+            if instruction.may_jump() {
+                let target = instruction.jump_target().unwrap();
+                match target.mode {
+                    ParamMode::Immediate => worklist.push(target.value),
+                    _ => dynamic_jump = true,
+                }
+            }
+            instructions.insert(addr, instruction);
+        }
+
+        if dynamic_jump {
+            worklist.extend(
+                instructions
+                    .values()
+                    .filter_map(constant_store)
+                    .filter(|addr| !visited.contains(addr)),
+            );
+        }
+        if worklist.is_empty() {
+            break;
+        }
+    }
+
+    invalid.sort();
+    (instructions, invalid)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub start: Integer,
+    pub instructions: Vec<Instruction>,
+    pub succs: Vec<Integer>,
+    pub call: Option<Integer>,
+}
+
+impl Block {
+    pub fn end(&self) -> Integer {
+        self.instructions
+            .last()
+            .map_or(self.start, Instruction::next)
+    }
+
+    pub fn last(&self) -> &Instruction {
+        self.instructions.last().unwrap()
+    }
+}
+
+/// Target of a call site: an unconditional immediate jump preceded, within
+/// the same straight-line code, by a store of the address right after it.
+/// That is how both the puzzle programs and our own compiler push their
+/// return address before jumping into a function.
+fn call_target(instructions: &[Instruction]) -> Option<Integer> {
+    let jump = instructions.last()?;
+    if jump.may_jump() && !jump.falls_through() {
+        let target = jump.jump_target()?;
+        let pushes_return = instructions
+            .iter()
+            .rev()
+            .skip(1)
+            .take(3)
+            .any(|i| constant_store(i) == Some(jump.next()));
+        if target.mode == ParamMode::Immediate && pushes_return {
+            return Some(target.value);
+        }
+    }
+    None
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<Integer, Block>,
+    pub functions: BTreeSet<Integer>,
+}
+
+impl Cfg {
+    pub fn build(tape: &Tape) -> Self {
+        let (instructions, _) = reachable(tape);
+
+        let mut leaders = BTreeSet::from([0]);
+        for instruction in instructions.values() {
+            if instruction.jump_target().is_some() || instruction.opcode == OpCode::Eof {
+                leaders.insert(instruction.next());
+            }
+            if let Some(target) = instruction.jump_target() {
+                if target.mode == ParamMode::Immediate && instruction.may_jump() {
+                    leaders.insert(target.value);
+                }
+            }
+            if let Some(addr) = constant_store(instruction) {
+                leaders.insert(addr);
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut functions = BTreeSet::from([0]);
+        for &start in leaders
+            .iter()
+            .filter(|addr| instructions.contains_key(addr))
+        {
+            let mut block = Vec::new();
+            let mut addr = start;
+            while let Some(instruction) = instructions.get(&addr) {
+                if addr != start && leaders.contains(&addr) {
+                    break;
+                }
+                block.push(instruction.clone());
+                addr = instruction.next();
+                if instruction.jump_target().is_some() || instruction.opcode == OpCode::Eof {
+                    break;
+                }
+            }
+
+            let call = call_target(&block);
+            let last = block.last().unwrap();
+            let mut succs = Vec::new();
+            if let Some(target) = call {
+                functions.insert(target);
+                succs.push(last.next());
+            } else {
+                if last.may_jump() {
+                    let target = last.jump_target().unwrap();
+                    if target.mode == ParamMode::Immediate {
+                        succs.push(target.value);
+                    }
+                }
+                if last.falls_through() {
+                    succs.push(last.next());
+                }
+            }
+            succs.retain(|addr| instructions.contains_key(addr));
+
+            let block = Block {
+                start,
+                instructions: block,
+                succs,
+                call,
+            };
+            blocks.insert(start, block);
+        }
+
+        Self { blocks, functions }
+    }
+
+    /// Blocks of the function starting at `entry`, stepping over calls to
+    /// other functions rather than into them.
+    pub fn function_blocks(&self, entry: Integer) -> BTreeSet<Integer> {
+        let mut seen = BTreeSet::new();
+        let mut worklist = vec![entry];
+        while let Some(addr) = worklist.pop() {
+            if addr != entry && self.functions.contains(&addr) {
+                continue;
+            }
+            if let Some(block) = self.blocks.get(&addr) {
+                if seen.insert(addr) {
+                    worklist.extend(block.succs.iter().copied());
+                }
+            }
+        }
+        seen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn test_call_and_return() {
+        // Pushes return address 9, calls 12, which returns through [rb+0].
+        const TAPE: &str = "109,100,21101,9,0,0,1105,1,12,104,7,99,106,0,100";
+        let tape = Tape::from_str(TAPE).unwrap();
+        let (instructions, _) = reachable(&tape);
+        assert!(instructions.contains_key(&9));
+    }
+
+    #[test]
+    fn test_constant_store_overflow() {
+        // Stores MAX * 2, then jumps through [rb+0].
+        let tape = Tape::from_str("1102,9223372036854775807,2,8,2106,0,0,99").unwrap();
+        let instruction = Instruction::decode(&tape, 0).unwrap();
+        assert_eq!(constant_store(&instruction), None);
+        let (instructions, _) = reachable(&tape);
+        assert_eq!(instructions.keys().copied().collect::<Vec<_>>(), [0, 4]);
+    }
+
+    #[test]
+    fn test_blocks() {
+        // Calls 12 with return address 9; 12 counts [rb+1] down and returns.
+        const TAPE: &str = "109,100,21101,9,0,0,1105,1,12,104,7,99,21001,1,-1,1,1205,1,12,2106,0,0";
+        let tape = Tape::from_str(TAPE).unwrap();
+        let cfg = Cfg::build(&tape);
+
+        assert_eq!(cfg.functions, BTreeSet::from([0, 12]));
+        assert_eq!(cfg.blocks[&0].call, Some(12));
+        assert_eq!(cfg.blocks[&0].succs, vec![9]);
+        assert_eq!(cfg.blocks[&12].succs, vec![12, 19]);
+        assert_eq!(cfg.blocks[&19].succs, vec![]);
+        assert_eq!(cfg.function_blocks(0), BTreeSet::from([0, 9]));
+        assert_eq!(cfg.function_blocks(12), BTreeSet::from([12, 19]));
+    }
+}
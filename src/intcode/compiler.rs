@@ -0,0 +1,912 @@
+// Compiler from a tiny C-like language to Intcode.
+//
+//     fn square(x) { return x * x; }
+//
+//     fn main() {
+//         let n = input();
+//         let i = 0;
+//         while i < n {
+//             output(square(i));
+//             i = i + 1;
+//         }
+//     }
+//
+// Every value is an `Integer`. Execution starts at `main`. Locals, arguments
+// and temporaries live in a stack frame addressed through `relbase`, with the
+// return address in slot 0 and the arguments in the slots after it. Return
+// values are passed through a single cell placed right after the code.
+
+use std::collections::HashMap;
+
+use super::Integer;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("line {line}: {message}")]
+pub struct CompileError {
+    pub line: usize,
+    pub message: String,
+}
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T> {
+    Err(CompileError {
+        line,
+        message: message.into(),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Integer),
+    Ident(String),
+    Fn,
+    Let,
+    If,
+    Else,
+    While,
+    Return,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semicolon,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Bang,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Eof,
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            '0'..='9' => {
+                let mut number = String::from(c);
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    number.push(c);
+                }
+                match number.parse() {
+                    Ok(number) => Token::Number(number),
+                    Err(_) => return error(line, format!("number out of range: {number}")),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_') {
+                    ident.push(c);
+                }
+                match ident.as_str() {
+                    "fn" => Token::Fn,
+                    "let" => Token::Let,
+                    "if" => Token::If,
+                    "else" => Token::Else,
+                    "while" => Token::While,
+                    "return" => Token::Return,
+                    _ => Token::Ident(ident),
+                }
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Le,
+            '<' => Token::Lt,
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Ge,
+            '>' => Token::Gt,
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Eq,
+            '=' => Token::Assign,
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Ne,
+            '!' => Token::Bang,
+            c => return error(line, format!("unexpected character `{c}`")),
+        };
+        tokens.push((token, line));
+    }
+
+    tokens.push((Token::Eof, line));
+    Ok(tokens)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Number(Integer),
+    Var(String, usize),
+    Neg(Box<Expr>, usize),
+    Not(Box<Expr>, usize),
+    Binary(BinOp, Box<Expr>, Box<Expr>, usize),
+    Call(String, Vec<Expr>, usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr, usize),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Expr),
+    Expr(Expr),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+    line: usize,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<()> {
+        if self.eat(&token) {
+            Ok(())
+        } else {
+            error(
+                self.line(),
+                format!("expected {:?}, found {:?}", token, self.peek()),
+            )
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next() {
+            Token::Ident(name) => Ok(name),
+            token => error(self.line(), format!("expected identifier, found {token:?}")),
+        }
+    }
+
+    fn program(&mut self) -> Result<Vec<Function>> {
+        let mut functions = Vec::new();
+        while self.peek() != &Token::Eof {
+            functions.push(self.function()?);
+        }
+        Ok(functions)
+    }
+
+    fn function(&mut self) -> Result<Function> {
+        let line = self.line();
+        self.expect(Token::Fn)?;
+        let name = self.ident()?;
+        self.expect(Token::LParen)?;
+        let mut params = Vec::new();
+        if !self.eat(&Token::RParen) {
+            loop {
+                params.push(self.ident()?);
+                if self.eat(&Token::RParen) {
+                    break;
+                }
+                self.expect(Token::Comma)?;
+            }
+        }
+        let body = self.block()?;
+        Ok(Function {
+            name,
+            params,
+            body,
+            line,
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>> {
+        self.expect(Token::LBrace)?;
+        let mut stmts = Vec::new();
+        while !self.eat(&Token::RBrace) {
+            stmts.push(self.stmt()?);
+        }
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt> {
+        let line = self.line();
+        match self.peek() {
+            Token::Let => {
+                self.next();
+                let name = self.ident()?;
+                self.expect(Token::Assign)?;
+                let expr = self.expr()?;
+                self.expect(Token::Semicolon)?;
+                Ok(Stmt::Let(name, expr))
+            }
+            Token::If => {
+                self.next();
+                let cond = self.expr()?;
+                let then = self.block()?;
+                let otherwise = if !self.eat(&Token::Else) {
+                    Vec::new()
+                } else if self.peek() == &Token::If {
+                    vec![self.stmt()?]
+                } else {
+                    self.block()?
+                };
+                Ok(Stmt::If(cond, then, otherwise))
+            }
+            Token::While => {
+                self.next();
+                let cond = self.expr()?;
+                let body = self.block()?;
+                Ok(Stmt::While(cond, body))
+            }
+            Token::Return => {
+                self.next();
+                let expr = if self.peek() == &Token::Semicolon {
+                    Expr::Number(0)
+                } else {
+                    self.expr()?
+                };
+                self.expect(Token::Semicolon)?;
+                Ok(Stmt::Return(expr))
+            }
+            Token::Ident(_) if self.tokens[self.pos + 1].0 == Token::Assign => {
+                let name = self.ident()?;
+                self.expect(Token::Assign)?;
+                let expr = self.expr()?;
+                self.expect(Token::Semicolon)?;
+                Ok(Stmt::Assign(name, expr, line))
+            }
+            _ => {
+                let expr = self.expr()?;
+                self.expect(Token::Semicolon)?;
+                Ok(Stmt::Expr(expr))
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let lhs = self.additive()?;
+        let op = match self.peek() {
+            Token::Lt => BinOp::Lt,
+            Token::Le => BinOp::Le,
+            Token::Gt => BinOp::Gt,
+            Token::Ge => BinOp::Ge,
+            Token::Eq => BinOp::Eq,
+            Token::Ne => BinOp::Ne,
+            _ => return Ok(lhs),
+        };
+        let line = self.line();
+        self.next();
+        let rhs = self.additive()?;
+        Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs), line))
+    }
+
+    fn additive(&mut self) -> Result<Expr> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Token::Plus => BinOp::Add,
+                Token::Minus => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            let line = self.line();
+            self.next();
+            let rhs = self.multiplicative()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs), line);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        let mut line = self.line();
+        while self.eat(&Token::Star) {
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(BinOp::Mul, Box::new(lhs), Box::new(rhs), line);
+            line = self.line();
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let line = self.line();
+        if self.eat(&Token::Minus) {
+            Ok(Expr::Neg(Box::new(self.unary()?), line))
+        } else if self.eat(&Token::Bang) {
+            Ok(Expr::Not(Box::new(self.unary()?), line))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let line = self.line();
+        match self.next() {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Ident(name) if self.eat(&Token::LParen) => {
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(Token::Comma)?;
+                    }
+                }
+                Ok(Expr::Call(name, args, line))
+            }
+            Token::Ident(name) => Ok(Expr::Var(name, line)),
+            Token::LParen => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            token => error(line, format!("expected expression, found {token:?}")),
+        }
+    }
+}
+
+type Label = usize;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Operand {
+    Imm(Integer),
+    Label(Label),
+    Ret,
+    Slot(Integer),
+}
+
+impl Operand {
+    fn mode(&self) -> Integer {
+        match self {
+            Operand::Ret => 0,
+            Operand::Imm(_) | Operand::Label(_) => 1,
+            Operand::Slot(_) => 2,
+        }
+    }
+}
+
+const ADD: Integer = 1;
+const MUL: Integer = 2;
+const INPUT: Integer = 3;
+const OUTPUT: Integer = 4;
+const JUMP_IF_TRUE: Integer = 5;
+const JUMP_IF_FALSE: Integer = 6;
+const LESS_THAN: Integer = 7;
+const EQUALS: Integer = 8;
+const ADJUST_REL_BASE: Integer = 9;
+const HALT: Integer = 99;
+
+struct Codegen {
+    code: Vec<Integer>,
+    labels: Vec<Option<Integer>>,
+    fixups: Vec<(usize, Label)>,
+    functions: HashMap<String, (Label, usize)>,
+    vars: Vec<(String, Integer)>,
+    next_slot: Integer,
+    ret: Label,
+}
+
+impl Codegen {
+    fn new() -> Self {
+        Self {
+            code: Vec::new(),
+            labels: vec![None],
+            fixups: Vec::new(),
+            functions: HashMap::new(),
+            vars: Vec::new(),
+            next_slot: 0,
+            ret: 0,
+        }
+    }
+
+    fn label(&mut self) -> Label {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place(&mut self, label: Label) {
+        self.labels[label] = Some(self.code.len() as Integer);
+    }
+
+    fn emit(&mut self, opcode: Integer, operands: &[Operand]) {
+        let modes = operands
+            .iter()
+            .rev()
+            .fold(0, |modes, operand| modes * 10 + operand.mode());
+        self.code.push(modes * 100 + opcode);
+        for operand in operands {
+            match *operand {
+                Operand::Imm(value) | Operand::Slot(value) => self.code.push(value),
+                Operand::Label(label) => {
+                    self.fixups.push((self.code.len(), label));
+                    self.code.push(0);
+                }
+                Operand::Ret => {
+                    self.fixups.push((self.code.len(), self.ret));
+                    self.code.push(0);
+                }
+            }
+        }
+    }
+
+    fn jump(&mut self, label: Label) {
+        self.emit(JUMP_IF_TRUE, &[Operand::Imm(1), Operand::Label(label)]);
+    }
+
+    fn alloc(&mut self) -> Operand {
+        self.next_slot += 1;
+        Operand::Slot(self.next_slot - 1)
+    }
+
+    fn free(&mut self, operand: Operand) {
+        if let Operand::Slot(slot) = operand {
+            if slot >= self.next_slot - 1 && !self.vars.iter().any(|(_, s)| *s == slot) {
+                self.next_slot = slot;
+            }
+        }
+    }
+
+    fn lookup(&self, name: &str, line: usize) -> Result<Operand> {
+        match self.vars.iter().rev().find(|(var, _)| var == name) {
+            Some((_, slot)) => Ok(Operand::Slot(*slot)),
+            None => error(line, format!("unknown variable `{name}`")),
+        }
+    }
+
+    fn program(&mut self, functions: &[Function]) -> Result<Vec<Integer>> {
+        for function in functions {
+            let label = self.label();
+            let entry = (label, function.params.len());
+            if self
+                .functions
+                .insert(function.name.clone(), entry)
+                .is_some()
+            {
+                return error(
+                    function.line,
+                    format!("duplicate function `{}`", function.name),
+                );
+            }
+        }
+        let Some(&(main, 0)) = self.functions.get("main") else {
+            return error(1, "missing `fn main()` without parameters");
+        };
+
+        let stack = self.label();
+        let halt = self.label();
+        self.emit(ADJUST_REL_BASE, &[Operand::Label(stack)]);
+        self.emit(
+            ADD,
+            &[Operand::Label(halt), Operand::Imm(0), Operand::Slot(0)],
+        );
+        self.jump(main);
+        self.place(halt);
+        self.emit(HALT, &[]);
+
+        for function in functions {
+            self.function(function)?;
+        }
+
+        self.place(self.ret);
+        self.code.push(0);
+        self.place(stack);
+
+        for &(pos, label) in &self.fixups {
+            self.code[pos] = self.labels[label].expect("label was never placed");
+        }
+        Ok(std::mem::take(&mut self.code))
+    }
+
+    fn function(&mut self, function: &Function) -> Result<()> {
+        let (label, _) = self.functions[&function.name];
+        self.place(label);
+
+        self.vars = function
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| (param.clone(), 1 + i as Integer))
+            .collect();
+        self.next_slot = 1 + function.params.len() as Integer;
+
+        self.block(&function.body)?;
+        if !matches!(function.body.last(), Some(Stmt::Return(_))) {
+            self.ret_value(Operand::Imm(0));
+        }
+        Ok(())
+    }
+
+    fn ret_value(&mut self, value: Operand) {
+        self.emit(ADD, &[value, Operand::Imm(0), Operand::Ret]);
+        self.emit(JUMP_IF_FALSE, &[Operand::Imm(0), Operand::Slot(0)]);
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<()> {
+        let (vars, next_slot) = (self.vars.len(), self.next_slot);
+        for stmt in stmts {
+            self.stmt(stmt)?;
+        }
+        self.vars.truncate(vars);
+        self.next_slot = next_slot;
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        match stmt {
+            Stmt::Let(name, expr) => {
+                let slot = self.alloc();
+                self.expr_into(expr, slot)?;
+                if let Operand::Slot(slot) = slot {
+                    self.vars.push((name.clone(), slot));
+                }
+            }
+            Stmt::Assign(name, expr, line) => {
+                let slot = self.lookup(name, *line)?;
+                self.expr_into(expr, slot)?;
+            }
+            Stmt::If(cond, then, otherwise) => {
+                let else_label = self.label();
+                let end_label = self.label();
+                let cond = self.expr(cond)?;
+                self.free(cond);
+                self.emit(JUMP_IF_FALSE, &[cond, Operand::Label(else_label)]);
+                self.block(then)?;
+                if !otherwise.is_empty() {
+                    self.jump(end_label);
+                }
+                self.place(else_label);
+                self.block(otherwise)?;
+                self.place(end_label);
+            }
+            Stmt::While(cond, body) => {
+                let top_label = self.label();
+                let end_label = self.label();
+                self.place(top_label);
+                let cond = self.expr(cond)?;
+                self.free(cond);
+                self.emit(JUMP_IF_FALSE, &[cond, Operand::Label(end_label)]);
+                self.block(body)?;
+                self.jump(top_label);
+                self.place(end_label);
+            }
+            Stmt::Return(expr) => {
+                let value = self.expr(expr)?;
+                self.free(value);
+                self.ret_value(value);
+            }
+            Stmt::Expr(expr) => {
+                let value = self.expr(expr)?;
+                self.free(value);
+            }
+        }
+        Ok(())
+    }
+
+    fn expr_into(&mut self, expr: &Expr, dst: Operand) -> Result<()> {
+        let value = self.expr(expr)?;
+        self.free(value);
+        if value != dst {
+            self.emit(ADD, &[value, Operand::Imm(0), dst]);
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<Operand> {
+        match expr {
+            Expr::Number(value) => Ok(Operand::Imm(*value)),
+            Expr::Var(name, line) => self.lookup(name, *line),
+            Expr::Neg(expr, line) => self.binary(BinOp::Mul, expr, &Expr::Number(-1), *line),
+            Expr::Not(expr, line) => self.binary(BinOp::Eq, expr, &Expr::Number(0), *line),
+            Expr::Binary(op, lhs, rhs, line) => self.binary(*op, lhs, rhs, *line),
+            Expr::Call(name, args, line) => self.call(name, args, *line),
+        }
+    }
+
+    fn binary(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr, line: usize) -> Result<Operand> {
+        let lhs = self.expr(lhs)?;
+        let rhs = self.expr(rhs)?;
+
+        if let (Operand::Imm(lhs), Operand::Imm(rhs)) = (lhs, rhs) {
+            let value = match op {
+                BinOp::Add => lhs.checked_add(rhs),
+                BinOp::Sub => lhs.checked_sub(rhs),
+                BinOp::Mul => lhs.checked_mul(rhs),
+                BinOp::Lt => Some((lhs < rhs) as Integer),
+                BinOp::Le => Some((lhs <= rhs) as Integer),
+                BinOp::Gt => Some((lhs > rhs) as Integer),
+                BinOp::Ge => Some((lhs >= rhs) as Integer),
+                BinOp::Eq => Some((lhs == rhs) as Integer),
+                BinOp::Ne => Some((lhs != rhs) as Integer),
+            };
+            return match value {
+                Some(value) => Ok(Operand::Imm(value)),
+                None => error(line, "constant expression overflows"),
+            };
+        }
+
+        // Operands are read before the destination is written, so the result
+        // may reuse the slot of either operand.
+        let (opcode, lhs, rhs, negate) = match op {
+            BinOp::Add => (ADD, lhs, rhs, false),
+            BinOp::Mul => (MUL, lhs, rhs, false),
+            BinOp::Sub => {
+                let neg = self.alloc();
+                self.emit(MUL, &[rhs, Operand::Imm(-1), neg]);
+                self.free(neg);
+                (ADD, lhs, neg, false)
+            }
+            BinOp::Lt => (LESS_THAN, lhs, rhs, false),
+            BinOp::Gt => (LESS_THAN, rhs, lhs, false),
+            BinOp::Le => (LESS_THAN, rhs, lhs, true),
+            BinOp::Ge => (LESS_THAN, lhs, rhs, true),
+            BinOp::Eq => (EQUALS, lhs, rhs, false),
+            BinOp::Ne => (EQUALS, lhs, rhs, true),
+        };
+        self.free(rhs);
+        self.free(lhs);
+        let dst = self.alloc();
+        self.emit(opcode, &[lhs, rhs, dst]);
+        if negate {
+            self.emit(EQUALS, &[dst, Operand::Imm(0), dst]);
+        }
+        Ok(dst)
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) -> Result<Operand> {
+        match (name, args) {
+            ("input", []) => {
+                let dst = self.alloc();
+                self.emit(INPUT, &[dst]);
+                return Ok(dst);
+            }
+            ("output", [arg]) => {
+                let value = self.expr(arg)?;
+                self.free(value);
+                self.emit(OUTPUT, &[value]);
+                return Ok(Operand::Imm(0));
+            }
+            ("input" | "output", _) => {
+                return error(line, format!("wrong number of arguments to `{name}`"));
+            }
+            _ => {}
+        }
+
+        let Some(&(label, arity)) = self.functions.get(name) else {
+            return error(line, format!("unknown function `{name}`"));
+        };
+        if arity != args.len() {
+            return error(
+                line,
+                format!("`{name}` takes {arity} arguments, {} given", args.len()),
+            );
+        }
+
+        // The callee's frame starts at `base`, above everything in use here:
+        // its return address goes into slot 0 and its arguments after that.
+        let base = self.next_slot;
+        let ret_slot = self.alloc();
+        for arg in args {
+            let slot = self.alloc();
+            let value = self.expr(arg)?;
+            self.free(value);
+            if value != slot {
+                self.emit(ADD, &[value, Operand::Imm(0), slot]);
+            }
+        }
+
+        let after = self.label();
+        self.emit(ADD, &[Operand::Label(after), Operand::Imm(0), ret_slot]);
+        self.emit(ADJUST_REL_BASE, &[Operand::Imm(base)]);
+        self.jump(label);
+        self.place(after);
+        self.emit(ADJUST_REL_BASE, &[Operand::Imm(-base)]);
+
+        self.next_slot = base;
+        let dst = self.alloc();
+        self.emit(ADD, &[Operand::Ret, Operand::Imm(0), dst]);
+        Ok(dst)
+    }
+}
+
+pub fn compile(source: &str) -> std::result::Result<Vec<Integer>, CompileError> {
+    let tokens = tokenize(source)?;
+    let functions = Parser { tokens, pos: 0 }.program()?;
+    Codegen::new().program(&functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::intcode::lint::{lint, Lint};
+    use crate::intcode::Tape;
+
+    fn run(source: &str, input: &[Integer]) -> Vec<Integer> {
+        let mut tape = Tape::from(compile(source).unwrap());
+        let (status, output) = tape.run_with(input);
+        assert!(status.is_halt());
+        output
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        const SOURCE: &str = r#"
+            fn main() {
+                let a = input();
+                let b = input();
+                output(a + b);
+                output(a - b);
+                output(a * b);
+                output(-a);
+                output(2 * (3 + 4) - 1);
+            }
+        "#;
+        assert_eq!(run(SOURCE, &[7, 3]), vec![10, 4, 21, -7, 13]);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        const SOURCE: &str = r#"
+            fn main() {
+                let a = input();
+                let b = input();
+                output(a < b);
+                output(a <= b);
+                output(a > b);
+                output(a >= b);
+                output(a == b);
+                output(a != b);
+                output(!a);
+            }
+        "#;
+        assert_eq!(run(SOURCE, &[1, 2]), vec![1, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(run(SOURCE, &[2, 2]), vec![0, 1, 0, 1, 1, 0, 0]);
+        assert_eq!(run(SOURCE, &[0, -1]), vec![0, 0, 1, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_control_flow() {
+        const SOURCE: &str = r#"
+            fn main() {
+                let n = input();
+                while n != 0 {
+                    if n < 0 {
+                        output(-1);
+                    } else if n == 0 {
+                        output(0);
+                    } else {
+                        output(1);
+                    }
+                    n = input();
+                }
+            }
+        "#;
+        assert_eq!(run(SOURCE, &[5, -3, 2, 0]), vec![1, -1, 1]);
+    }
+
+    #[test]
+    fn test_recursion() {
+        const SOURCE: &str = r#"
+            // Naive recursion exercises nested frames.
+            fn fib(n) {
+                if n < 2 {
+                    return n;
+                }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            fn fact(n) {
+                if n == 0 {
+                    return 1;
+                }
+                return n * fact(n - 1);
+            }
+
+            fn main() {
+                output(fib(input()));
+                output(fact(input()));
+            }
+        "#;
+        assert_eq!(run(SOURCE, &[15, 10]), vec![610, 3628800]);
+    }
+
+    #[test]
+    fn test_nested_calls() {
+        const SOURCE: &str = r#"
+            fn sub(a, b) { return a - b; }
+            fn twice(x) { let y = x * 2; return y; }
+
+            fn main() {
+                let x = 100;
+                output(sub(twice(x), sub(x, twice(3))));
+                output(x);
+            }
+        "#;
+        assert_eq!(run(SOURCE, &[]), vec![106, 100]);
+    }
+
+    #[test]
+    fn test_output_is_clean() {
+        const SOURCE: &str = r#"
+            fn max(a, b) { if a > b { return a; } return b; }
+            fn main() { output(max(input(), input())); }
+        "#;
+        let tape = Tape::from(compile(SOURCE).unwrap());
+        let findings: Vec<_> = lint(&tape)
+            .into_iter()
+            .filter(|finding| finding.lint != Lint::UnreachableCode)
+            .collect();
+        assert_eq!(findings, vec![]);
+        assert_eq!(run(SOURCE, &[3, 9]), vec![9]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let line = |source: &str| compile(source).unwrap_err().line;
+        assert_eq!(line("fn main() {\n output(x);\n}"), 2);
+        assert_eq!(line("fn main() {\n\n f(1);\n}"), 3);
+        assert_eq!(line("fn f(a) {}\nfn main() { f(); }"), 2);
+        assert_eq!(line("fn main() { let = 1; }"), 1);
+        assert_eq!(line("fn f() {}"), 1);
+        assert_eq!(
+            line("fn main() {\n let x = 9999999999\n  * 9999999999;\n}"),
+            3
+        );
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use super::cfg::{constant_store, Cfg};
+use super::disasm::{Instruction, Param};
+use super::{Integer, OpCode, ParamMode, Tape};
+
+enum Cond {
+    Truthy(String),
+    Cmp(String, &'static str, String),
+}
+
+impl Cond {
+    fn negate(self) -> Self {
+        match self {
+            Cond::Truthy(value) => Cond::Cmp(value, "==", "0".into()),
+            Cond::Cmp(lhs, op, rhs) => {
+                let op = match op {
+                    "<" => ">=",
+                    ">=" => "<",
+                    "==" => "!=",
+                    _ => "==",
+                };
+                Cond::Cmp(lhs, op, rhs)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cond::Truthy(value) => write!(f, "{value}"),
+            Cond::Cmp(lhs, "!=", rhs) if rhs == "0" => write!(f, "{lhs}"),
+            Cond::Cmp(lhs, op, rhs) => write!(f, "{lhs} {op} {rhs}"),
+        }
+    }
+}
+
+struct Line {
+    addr: Integer,
+    indent: usize,
+    text: String,
+}
+
+struct Function<'a> {
+    instructions: BTreeMap<Integer, &'a Instruction>,
+    calls: &'a HashMap<Integer, Integer>,
+    return_stores: &'a HashSet<Integer>,
+    lines: Vec<Line>,
+    gotos: BTreeSet<Integer>,
+    loops: Vec<(Integer, Integer)>,
+    offset: Option<Integer>,
+}
+
+impl Function<'_> {
+    /// Name of a cell. Frame cells are numbered from the `relbase` the function
+    /// was entered with, so the same slot keeps its name across `arb`s.
+    fn name(&self, param: &Param) -> String {
+        match (param.mode, self.offset) {
+            (ParamMode::Immediate, _) => param.value.to_string(),
+            (ParamMode::Position, _) => format!("var{}", param.value),
+            (ParamMode::Relative, Some(offset)) if offset + param.value >= 0 => {
+                format!("local{}", offset + param.value)
+            }
+            (ParamMode::Relative, Some(offset)) => format!("arg{}", -(offset + param.value)),
+            (ParamMode::Relative, None) => format!("{param}"),
+        }
+    }
+
+    fn prev(&self, addr: Integer) -> Option<&Instruction> {
+        let (_, prev) = self.instructions.range(..addr).next_back()?;
+        (prev.next() == addr).then_some(*prev)
+    }
+
+    fn is_jump(instruction: &Instruction) -> bool {
+        instruction.jump_target().is_some()
+    }
+
+    fn is_goto(&self, instruction: &Instruction) -> bool {
+        Self::is_jump(instruction)
+            && !instruction.falls_through()
+            && !self.calls.contains_key(&instruction.addr)
+            && instruction.jump_target().unwrap().mode == ParamMode::Immediate
+    }
+
+    fn is_branch(instruction: &Instruction) -> bool {
+        Self::is_jump(instruction)
+            && instruction.falls_through()
+            && instruction.may_jump()
+            && instruction.jump_target().unwrap().mode == ParamMode::Immediate
+    }
+
+    /// Compare whose result is only tested by the branch right after it.
+    fn folds_into_next(&self, instruction: &Instruction) -> bool {
+        if !matches!(
+            instruction.opcode,
+            OpCode::LessThan(..) | OpCode::Equals(..)
+        ) {
+            return false;
+        }
+        match self.instructions.get(&instruction.next()) {
+            Some(next) => Self::is_branch(next) && next.params[0] == instruction.dst().unwrap(),
+            None => false,
+        }
+    }
+
+    /// Condition under which the branch `instruction` is taken.
+    fn jump_cond(&self, instruction: &Instruction) -> Cond {
+        let cnd = &instruction.params[0];
+        let cond = match self.prev(instruction.addr) {
+            Some(prev) if self.folds_into_next(prev) => {
+                let op = match prev.opcode {
+                    OpCode::LessThan(..) => "<",
+                    _ => "==",
+                };
+                Cond::Cmp(self.name(&prev.params[0]), op, self.name(&prev.params[1]))
+            }
+            _ => Cond::Truthy(self.name(cnd)),
+        };
+        match instruction.opcode {
+            OpCode::JumpIfFalse(..) => cond.negate(),
+            _ => cond,
+        }
+    }
+
+    fn statement(&mut self, instruction: &Instruction) -> Option<String> {
+        let p = &instruction.params;
+        let text = match instruction.opcode {
+            OpCode::Add(..) if p[1].mode == ParamMode::Immediate && p[1].value == 0 => {
+                format!("{} = {};", self.name(&p[2]), self.name(&p[0]))
+            }
+            OpCode::Add(..) if p[0].mode == ParamMode::Immediate && p[0].value == 0 => {
+                format!("{} = {};", self.name(&p[2]), self.name(&p[1]))
+            }
+            OpCode::Add(..) if p[1].mode == ParamMode::Immediate && p[1].value < 0 => {
+                let (dst, lhs) = (self.name(&p[2]), self.name(&p[0]));
+                format!("{dst} = {lhs} - {};", -p[1].value)
+            }
+            OpCode::Add(..) => format!(
+                "{} = {} + {};",
+                self.name(&p[2]),
+                self.name(&p[0]),
+                self.name(&p[1])
+            ),
+            OpCode::Mul(..) if p[1].mode == ParamMode::Immediate && p[1].value == -1 => {
+                format!("{} = -{};", self.name(&p[2]), self.name(&p[0]))
+            }
+            OpCode::Mul(..) => format!(
+                "{} = {} * {};",
+                self.name(&p[2]),
+                self.name(&p[0]),
+                self.name(&p[1])
+            ),
+            OpCode::LessThan(..) => format!(
+                "{} = {} < {};",
+                self.name(&p[2]),
+                self.name(&p[0]),
+                self.name(&p[1])
+            ),
+            OpCode::Equals(..) => format!(
+                "{} = {} == {};",
+                self.name(&p[2]),
+                self.name(&p[0]),
+                self.name(&p[1])
+            ),
+            OpCode::Input(..) => format!("{} = input();", self.name(&p[0])),
+            OpCode::Output(..) => format!("output({});", self.name(&p[0])),
+            OpCode::AdjustRelBase(ParamMode::Immediate) => {
+                self.offset = self.offset.map(|offset| offset + p[0].value);
+                return None;
+            }
+            OpCode::AdjustRelBase(..) => {
+                let text = format!("rb += {};", self.name(&p[0]));
+                self.offset = None;
+                text
+            }
+            OpCode::Eof => "halt();".into(),
+            OpCode::JumpIfTrue(..) | OpCode::JumpIfFalse(..) => unreachable!(),
+        };
+        Some(text)
+    }
+
+    fn push(&mut self, addr: Integer, indent: usize, text: String) {
+        self.lines.push(Line { addr, indent, text });
+    }
+
+    fn jump_to(&mut self, target: Integer) -> String {
+        match self.loops.last() {
+            Some(&(header, _)) if header == target => "continue;".into(),
+            Some(&(_, exit)) if exit == target => "break;".into(),
+            _ => {
+                self.gotos.insert(target);
+                format!("goto L{target};")
+            }
+        }
+    }
+
+    /// Last unconditional jump back to `header` before `end`, if `header`
+    /// starts a loop.
+    fn back_jump(&self, header: Integer, end: Integer) -> Option<&Instruction> {
+        if self.loops.last().is_some_and(|&(h, _)| h == header) {
+            return None;
+        }
+        self.instructions
+            .range(header + 1..end)
+            .rev()
+            .map(|(_, instruction)| *instruction)
+            .find(|i| self.is_goto(i) && i.jump_target().unwrap().value == header)
+    }
+
+    fn region(&mut self, start: Integer, end: Integer, indent: usize) {
+        let mut pc = start;
+        while let Some((&addr, &instruction)) = self.instructions.range(pc..end).next() {
+            pc = addr;
+
+            if let Some(back) = self.back_jump(pc, end) {
+                let (back_addr, exit) = (back.addr, back.next());
+
+                // A loop whose header only tests the exit condition is a while.
+                let mut test = instruction;
+                if self.folds_into_next(test) {
+                    test = self.instructions[&test.next()];
+                }
+                let header_exits = Self::is_branch(test)
+                    && test.jump_target().unwrap().value == exit
+                    && test.addr < back_addr;
+
+                if header_exits {
+                    let cond = self.jump_cond(test).negate();
+                    self.push(pc, indent, format!("while ({cond}) {{"));
+                    self.loops.push((pc, exit));
+                    self.region(test.next(), back_addr, indent + 1);
+                } else {
+                    self.push(pc, indent, "while (1) {".into());
+                    self.loops.push((pc, exit));
+                    self.region(pc, back_addr, indent + 1);
+                }
+                self.loops.pop();
+                self.push(back_addr, indent, "}".into());
+                pc = exit;
+                continue;
+            }
+
+            pc = instruction.next();
+
+            if self.folds_into_next(instruction) || self.return_stores.contains(&addr) {
+                continue;
+            }
+
+            if let Some(&target) = self.calls.get(&addr) {
+                self.push(addr, indent, format!("f{target}();"));
+                continue;
+            }
+
+            let Some(target) = instruction.jump_target() else {
+                if let Some(text) = self.statement(instruction) {
+                    self.push(addr, indent, text);
+                }
+                continue;
+            };
+
+            if target.mode != ParamMode::Immediate {
+                let text = match target.mode {
+                    ParamMode::Relative => "return;".into(),
+                    _ => format!("goto *{};", self.name(&target)),
+                };
+                if instruction.falls_through() {
+                    let cond = self.jump_cond(instruction);
+                    self.push(addr, indent, format!("if ({cond}) {text}"));
+                } else {
+                    self.push(addr, indent, text);
+                }
+                continue;
+            }
+
+            if !instruction.may_jump() {
+                continue;
+            }
+
+            if !instruction.falls_through() {
+                let text = self.jump_to(target.value);
+                self.push(addr, indent, text);
+                continue;
+            }
+
+            let target = target.value;
+            let is_loop_edge = self
+                .loops
+                .last()
+                .is_some_and(|&(header, exit)| target == header || target == exit);
+            if is_loop_edge || target <= addr || target > end {
+                let cond = self.jump_cond(instruction);
+                let text = self.jump_to(target);
+                self.push(addr, indent, format!("if ({cond}) {text}"));
+                continue;
+            }
+
+            let cond = self.jump_cond(instruction).negate();
+            self.push(addr, indent, format!("if ({cond}) {{"));
+
+            // The then-branch ending in a forward jump past `target` means
+            // there is an else-branch between the two.
+            let loop_exit = self.loops.last().map(|&(_, exit)| exit);
+            let otherwise = self
+                .instructions
+                .range(addr + 1..target)
+                .next_back()
+                .map(|(_, prev)| *prev)
+                .filter(|prev| {
+                    let join = prev.jump_target().map(|target| target.value);
+                    self.is_goto(prev)
+                        && join.is_some_and(|join| (target..=end).contains(&join))
+                        && join != loop_exit
+                });
+
+            match otherwise {
+                Some(jump) => {
+                    let (jump_addr, join) = (jump.addr, jump.jump_target().unwrap().value);
+                    self.region(pc, jump_addr, indent + 1);
+                    self.push(jump_addr, indent, "} else {".into());
+                    self.region(target, join, indent + 1);
+                    pc = join;
+                }
+                None => {
+                    self.region(pc, target, indent + 1);
+                    pc = target;
+                }
+            }
+            self.push(pc, indent, "}".into());
+        }
+    }
+}
+
+/// Turns a program back into C-like pseudo-code, one function per call
+/// target found in the control-flow graph. Position cells are named `varN`,
+/// frame cells `localN` relative to the function's entry `relbase`, and
+/// jumps that do not fit an `if`, `else` or `while` become `goto`s.
+pub fn decompile(tape: &Tape) -> String {
+    let cfg = Cfg::build(tape);
+
+    let mut calls = HashMap::new();
+    let mut return_stores = HashSet::new();
+    for block in cfg.blocks.values() {
+        if let Some(target) = block.call {
+            let jump = block.last();
+            calls.insert(jump.addr, target);
+            if let Some(store) = block
+                .instructions
+                .iter()
+                .rev()
+                .find(|i| constant_store(i) == Some(jump.next()))
+            {
+                return_stores.insert(store.addr);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for &entry in &cfg.functions {
+        let instructions: BTreeMap<Integer, &Instruction> = cfg
+            .function_blocks(entry)
+            .into_iter()
+            .flat_map(|block| cfg.blocks[&block].instructions.iter())
+            .map(|instruction| (instruction.addr, instruction))
+            .collect();
+        let end = instructions
+            .values()
+            .map(|i| i.next())
+            .max()
+            .unwrap_or(entry);
+
+        let mut function = Function {
+            instructions,
+            calls: &calls,
+            return_stores: &return_stores,
+            lines: Vec::new(),
+            gotos: BTreeSet::new(),
+            loops: Vec::new(),
+            offset: Some(0),
+        };
+        function.region(entry, end, 1);
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let name = if entry == 0 {
+            "main".to_string()
+        } else {
+            format!("f{entry}")
+        };
+        out.push_str(&format!("fn {name}() {{\n"));
+        let mut gotos = function.gotos.clone();
+        for line in &function.lines {
+            while let Some(&target) = gotos.first().filter(|&&target| target <= line.addr) {
+                gotos.remove(&target);
+                out.push_str(&format!("L{target}:\n"));
+            }
+            out.push_str(&format!("{}{}\n", "    ".repeat(line.indent), line.text));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::intcode::compiler::compile;
+
+    #[test]
+    fn test_if_else() {
+        // if (input() < 8) output(1) else output(2)
+        const TAPE: &str = "3,100,1007,100,8,101,1006,101,17,104,1,1105,1,19,99,99,99,104,2,99";
+        let tape = Tape::from_str(TAPE).unwrap();
+        assert_eq!(
+            decompile(&tape),
+            "fn main() {\n    var100 = input();\n    if (var100 < 8) {\n        output(1);\n    } else {\n        output(2);\n    }\n    halt();\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_compiled() {
+        const SOURCE: &str = r#"
+            fn square(x) { return x * x; }
+
+            fn main() {
+                let n = input();
+                let i = 0;
+                while i < n {
+                    if i == 3 {
+                        output(0);
+                    } else {
+                        output(square(i));
+                    }
+                    i = i + 1;
+                }
+            }
+        "#;
+        let tape = Tape::from(compile(SOURCE).unwrap());
+        let pseudo = decompile(&tape);
+
+        assert_eq!(pseudo.matches("fn ").count(), 3, "{pseudo}");
+        assert!(pseudo.contains("while (local2 < local1) {"), "{pseudo}");
+        assert!(pseudo.contains("if (local2 == 3) {"), "{pseudo}");
+        assert!(pseudo.contains("} else {"), "{pseudo}");
+        assert!(pseudo.contains("local3 = local2 + 1;"), "{pseudo}");
+        assert!(pseudo.contains("local2 = local1 * local1;"), "{pseudo}");
+        assert!(pseudo.contains("return;"), "{pseudo}");
+        assert!(!pseudo.contains("goto"), "{pseudo}");
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use super::{Integer, OpCode, ParamMode, Tape};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Param {
+    pub mode: ParamMode,
+    pub value: Integer,
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            ParamMode::Position => write!(f, "[{}]", self.value),
+            ParamMode::Immediate => write!(f, "{}", self.value),
+            ParamMode::Relative if self.value < 0 => write!(f, "[rb-{}]", -self.value),
+            ParamMode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub addr: Integer,
+    pub opcode: OpCode,
+    pub params: Vec<Param>,
+}
+
+impl Instruction {
+    pub fn decode(tape: &Tape, addr: Integer) -> Option<Self> {
+        if addr < 0 {
+            return None;
+        }
+        let opcode = OpCode::decode(tape.get(addr))?;
+        let params = opcode
+            .modes()
+            .into_iter()
+            .enumerate()
+            .map(|(i, mode)| Param {
+                mode,
+                value: tape.get(addr + 1 + i as Integer),
+            })
+            .collect();
+        Some(Self {
+            addr,
+            opcode,
+            params,
+        })
+    }
+
+    /// Number of cells the instruction occupies.
+    pub fn size(&self) -> Integer {
+        1 + self.params.len() as Integer
+    }
+
+    pub fn next(&self) -> Integer {
+        self.addr + self.size()
+    }
+
+    pub fn dst(&self) -> Option<Param> {
+        self.opcode.dst_index().map(|i| self.params[i])
+    }
+
+    /// Parameters the instruction reads from, i.e. everything except `dst`.
+    pub fn srcs(&self) -> impl Iterator<Item = &Param> {
+        let dst = self.opcode.dst_index();
+        self.params
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| Some(*i) != dst)
+            .map(|(_, param)| param)
+    }
+
+    pub fn jump_target(&self) -> Option<Param> {
+        match self.opcode {
+            OpCode::JumpIfTrue(..) | OpCode::JumpIfFalse(..) => Some(self.params[1]),
+            _ => None,
+        }
+    }
+
+    /// Whether execution can continue with the instruction right after this
+    /// one. A conditional jump on an immediate condition only goes one way.
+    pub fn falls_through(&self) -> bool {
+        match (self.opcode, self.params.first()) {
+            (OpCode::Eof, _) => false,
+            (OpCode::JumpIfTrue(ParamMode::Immediate, _), Some(cnd)) => cnd.value == 0,
+            (OpCode::JumpIfFalse(ParamMode::Immediate, _), Some(cnd)) => cnd.value != 0,
+            _ => true,
+        }
+    }
+
+    /// Whether the jump can be taken at all.
+    pub fn may_jump(&self) -> bool {
+        match (self.opcode, self.params.first()) {
+            (OpCode::JumpIfTrue(ParamMode::Immediate, _), Some(cnd)) => cnd.value != 0,
+            (OpCode::JumpIfFalse(ParamMode::Immediate, _), Some(cnd)) => cnd.value == 0,
+            (OpCode::JumpIfTrue(..) | OpCode::JumpIfFalse(..), _) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>6}: {:<4}", self.addr, self.opcode.mnemonic())?;
+        for (i, param) in self.params.iter().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{sep}{param}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Line {
+    Instruction(Instruction),
+    Data(Integer, Integer),
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Instruction(instruction) => write!(f, "{instruction}"),
+            Line::Data(addr, value) => write!(f, "{addr:>6}: .data {value}"),
+        }
+    }
+}
+
+/// Linear sweep over the whole tape. Cells that do not decode, or whose
+/// instruction would run past the end of the tape, are emitted as data.
+pub fn disassemble(tape: &Tape) -> Vec<Line> {
+    let len = tape.memory().len() as Integer;
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < len {
+        match Instruction::decode(tape, addr) {
+            Some(instruction) if instruction.next() <= len => {
+                addr = instruction.next();
+                lines.push(Line::Instruction(instruction));
+            }
+            _ => {
+                lines.push(Line::Data(addr, tape.get(addr)));
+                addr += 1;
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn test_disassemble() {
+        let tape = Tape::from_str("1002,4,3,4,33,109,-1,21101,1,2,3,99,-7").unwrap();
+        let listing: Vec<String> = disassemble(&tape).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            listing,
+            vec![
+                "     0: mul  [4], 3, [4]",
+                "     4: .data 33",
+                "     5: arb  -1",
+                "     7: add  1, 2, [rb+3]",
+                "    11: halt",
+                "    12: .data -7",
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use super::Integer;
+
+/// A complete record decoded from a machine's output stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Frame {
+    Record(Vec<Integer>),
+    /// A record starting with one of the registered special prefixes.
+    Special(usize, Vec<Integer>),
+}
+
+/// Groups a flat output stream into fixed-size records.
+#[derive(Clone, Debug)]
+pub struct Frames {
+    size: usize,
+    specials: Vec<Vec<Integer>>,
+    pending: Vec<Integer>,
+}
+
+impl Frames {
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "frames must hold at least one value");
+        Self {
+            size,
+            specials: Vec::new(),
+            pending: Vec::with_capacity(size),
+        }
+    }
+
+    /// Registers a prefix that marks a record as special. Specials are
+    /// numbered in registration order.
+    pub fn special(mut self, prefix: &[Integer]) -> Self {
+        assert!(prefix.len() <= self.size, "prefix longer than a frame");
+        self.specials.push(prefix.to_vec());
+        self
+    }
+
+    /// Feeds one output value, returning the frame it completes, if any.
+    pub fn push(&mut self, value: Integer) -> Option<Frame> {
+        self.pending.push(value);
+        if self.pending.len() < self.size {
+            return None;
+        }
+
+        let record = std::mem::replace(&mut self.pending, Vec::with_capacity(self.size));
+        let special = self
+            .specials
+            .iter()
+            .position(|prefix| record.starts_with(prefix));
+        Some(match special {
+            Some(index) => Frame::Special(index, record),
+            None => Frame::Record(record),
+        })
+    }
+
+    /// Number of values received towards the next frame.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Sparse 2D buffer addressed by `(x, y)`, growing in every direction.
+#[derive(Clone, Debug)]
+pub struct Screen<T> {
+    cells: HashMap<(Integer, Integer), T>,
+}
+
+impl<T> Default for Screen<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Screen<T> {
+    pub fn get(&self, pos: (Integer, Integer)) -> Option<&T> {
+        self.cells.get(&pos)
+    }
+
+    pub fn set(&mut self, pos: (Integer, Integer), value: T) {
+        self.cells.insert(pos, value);
+    }
+
+    /// Number of cells ever written.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.cells.values()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((Integer, Integer), &T)> {
+        self.cells.iter().map(|(&pos, value)| (pos, value))
+    }
+
+    /// Smallest and largest written coordinates, inclusive.
+    pub fn bounds(&self) -> Option<((Integer, Integer), (Integer, Integer))> {
+        let mut positions = self.cells.keys();
+        let &first = positions.next()?;
+        Some(positions.fold((first, first), |(min, max), &(x, y)| {
+            ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+        }))
+    }
+
+    /// Draws the written area row by row, one character per cell.
+    pub fn render(&self, draw: impl Fn(Option<&T>) -> char) -> String {
+        let Some(((min_x, min_y), (max_x, max_y))) = self.bounds() else {
+            return String::new();
+        };
+        let mut out = String::new();
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                out.push(draw(self.get((x, y))));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let mut frames = Frames::new(3).special(&[-1, 0]);
+        let decoded: Vec<_> = [1, 2, 3, -1, 0, 42, -1, 1, 4, 5]
+            .into_iter()
+            .filter_map(|value| frames.push(value))
+            .collect();
+
+        assert_eq!(
+            decoded,
+            vec![
+                Frame::Record(vec![1, 2, 3]),
+                Frame::Special(0, vec![-1, 0, 42]),
+                Frame::Record(vec![-1, 1, 4]),
+            ]
+        );
+        assert_eq!(frames.pending(), 1);
+    }
+
+    #[test]
+    fn test_screen() {
+        let mut screen = Screen::default();
+        assert_eq!(screen.bounds(), None);
+        assert_eq!(screen.render(|_| '#'), "");
+
+        screen.set((-1, 0), true);
+        screen.set((1, 1), false);
+        screen.set((1, 1), true);
+        assert_eq!(screen.len(), 2);
+        assert_eq!(screen.bounds(), Some(((-1, 0), (1, 1))));
+        assert_eq!(
+            screen.render(|cell| if cell.is_some() { '#' } else { '.' }),
+            "#..\n..#\n"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::Write as _;
+use std::io::Write;
+use std::num::NonZeroUsize;
+
+use termcolor::WriteColor;
+
+use super::disasm::Instruction;
+use super::{Integer, ParamMode};
+
+/// Addresses past this are counted as out of range rather than given a cell,
+/// so that a far-off write does not make every rendering enormous.
+pub const MAX_ADDR: usize = 1 << 20;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Heatmap {
+    reads: Vec<u64>,
+    writes: Vec<u64>,
+    execs: Vec<u64>,
+    out_of_range: u64,
+}
+
+impl Heatmap {
+    fn bump(counts: &mut Vec<u64>, out_of_range: &mut u64, addr: Integer) {
+        match usize::try_from(addr) {
+            Ok(addr) if addr < MAX_ADDR => {
+                if addr >= counts.len() {
+                    counts.resize(addr + 1, 0);
+                }
+                counts[addr] += 1;
+            }
+            _ => *out_of_range += 1,
+        }
+    }
+}
+
+fn count(counts: &[u64], addr: usize) -> u64 {
+    counts.get(addr).copied().unwrap_or(0)
+}
+
+/// Maps a count onto 0..=255 on a log scale, so that hot loops do not wash
+/// out everything that only runs a handful of times.
+fn intensity(count: u64, max: u64) -> u8 {
+    if count == 0 || max == 0 {
+        return 0;
+    }
+    let scaled = ((count + 1) as f64).ln() / ((max + 1) as f64).ln();
+    (64.0 + 191.0 * scaled).round() as u8
+}
+
+impl Heatmap {
+    pub fn record(&mut self, instruction: &Instruction, relbase: Integer) {
+        for addr in instruction.addr..instruction.next() {
+            Self::bump(&mut self.execs, &mut self.out_of_range, addr);
+        }
+        let resolve = |mode: ParamMode, value: Integer| match mode {
+            ParamMode::Position => Some(value),
+            ParamMode::Immediate => None,
+            ParamMode::Relative => Some(relbase + value),
+        };
+        for src in instruction.srcs() {
+            if let Some(addr) = resolve(src.mode, src.value) {
+                Self::bump(&mut self.reads, &mut self.out_of_range, addr);
+            }
+        }
+        if let Some(dst) = instruction.dst() {
+            // Immediate destinations are written as if they were positional.
+            let mode = match dst.mode {
+                ParamMode::Immediate => ParamMode::Position,
+                mode => mode,
+            };
+            if let Some(addr) = resolve(mode, dst.value) {
+                Self::bump(&mut self.writes, &mut self.out_of_range, addr);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.reads
+            .len()
+            .max(self.writes.len())
+            .max(self.execs.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn reads(&self, addr: usize) -> u64 {
+        count(&self.reads, addr)
+    }
+
+    pub fn writes(&self, addr: usize) -> u64 {
+        count(&self.writes, addr)
+    }
+
+    pub fn execs(&self, addr: usize) -> u64 {
+        count(&self.execs, addr)
+    }
+
+    /// Accesses to negative addresses or addresses from `MAX_ADDR` on.
+    pub fn out_of_range(&self) -> u64 {
+        self.out_of_range
+    }
+
+    /// Colour of every cell: red for writes, green for execution, blue for
+    /// reads.
+    fn colors(&self) -> Vec<(u8, u8, u8)> {
+        let max = |counts: &[u64]| counts.iter().copied().max().unwrap_or(0);
+        let (max_r, max_g, max_b) = (max(&self.writes), max(&self.execs), max(&self.reads));
+        (0..self.len())
+            .map(|addr| {
+                (
+                    intensity(self.writes(addr), max_r),
+                    intensity(self.execs(addr), max_g),
+                    intensity(self.reads(addr), max_b),
+                )
+            })
+            .collect()
+    }
+
+    fn height(&self, width: usize) -> usize {
+        self.len().div_ceil(width).max(1)
+    }
+
+    /// Binary PPM (P6) image with one pixel per address, `width` per row.
+    pub fn to_ppm(&self, width: NonZeroUsize) -> Vec<u8> {
+        let width = width.get();
+        let height = self.height(width);
+        let mut colors = self.colors();
+        colors.resize(width * height, (0, 0, 0));
+
+        let mut out = format!("P6\n{width} {height}\n255\n").into_bytes();
+        for (r, g, b) in colors {
+            out.extend([r, g, b]);
+        }
+        out
+    }
+
+    /// SVG image with one `scale` sized square per address, `width` per row.
+    /// Each square carries a tooltip with its counts.
+    pub fn to_svg(&self, width: NonZeroUsize, scale: NonZeroUsize) -> String {
+        let (width, scale) = (width.get(), scale.get());
+        let height = self.height(width);
+        let mut out = String::new();
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+            width * scale,
+            height * scale
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"<rect width="100%" height="100%" fill="rgb(0,0,0)"/>"#
+        )
+        .unwrap();
+        for (addr, (r, g, b)) in self.colors().into_iter().enumerate() {
+            if (r, g, b) == (0, 0, 0) {
+                continue;
+            }
+            writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="{scale}" height="{scale}" fill="rgb({r},{g},{b})"><title>{addr}: r={} w={} x={}</title></rect>"#,
+                (addr % width) * scale,
+                (addr / width) * scale,
+                self.reads(addr),
+                self.writes(addr),
+                self.execs(addr),
+            )
+            .unwrap();
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Terminal rendering using 24-bit background colours, one cell per
+    /// address and `width` cells per row, each row prefixed by its address.
+    pub fn to_ansi(&self, width: NonZeroUsize) -> String {
+        let width = width.get();
+        let mut buf = termcolor::Buffer::ansi();
+        for (row, colors) in self.colors().chunks(width).enumerate() {
+            write!(buf, "{:>6} ", row * width).unwrap();
+            for &(r, g, b) in colors {
+                let mut cs = termcolor::ColorSpec::new();
+                cs.set_bg(Some(termcolor::Color::Rgb(r, g, b)));
+                buf.set_color(&cs).unwrap();
+                write!(buf, " ").unwrap();
+            }
+            buf.reset().unwrap();
+            writeln!(buf).unwrap();
+        }
+        String::from_utf8_lossy(buf.as_slice()).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::intcode::Tape;
+
+    // Counts [13] down from 3 and halts.
+    const COUNTDOWN: &str = "1101,3,0,13,1001,13,-1,13,1005,13,4,99,0,0";
+
+    fn heatmap() -> Heatmap {
+        let mut tape = Tape::from_str(COUNTDOWN).unwrap();
+        tape.record_heatmap();
+        tape.run_with(&[]);
+        tape.heatmap().unwrap().clone()
+    }
+
+    #[test]
+    fn test_counts() {
+        let heatmap = heatmap();
+        assert_eq!(heatmap.execs(0), 1);
+        assert_eq!(heatmap.execs(4), 3);
+        assert_eq!(heatmap.execs(11), 1);
+        assert_eq!(heatmap.execs(12), 0);
+        assert_eq!(heatmap.writes(13), 4);
+        assert_eq!(heatmap.reads(13), 6);
+    }
+
+    fn n(value: usize) -> NonZeroUsize {
+        NonZeroUsize::new(value).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let heatmap = heatmap();
+
+        let ppm = heatmap.to_ppm(n(8));
+        assert!(ppm.starts_with(b"P6\n8 2\n255\n"));
+        assert_eq!(ppm.len(), "P6\n8 2\n255\n".len() + 8 * 2 * 3);
+
+        let svg = heatmap.to_svg(n(8), n(4));
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="32" height="8">"#)
+        );
+        assert_eq!(svg.matches("<title>").count(), 13);
+
+        let ansi = heatmap.to_ansi(n(8));
+        assert_eq!(ansi.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_render_narrowest() {
+        let heatmap = heatmap();
+        assert!(heatmap.to_ppm(n(1)).starts_with(b"P6\n1 14\n255\n"));
+        assert!(heatmap
+            .to_svg(n(1), n(1))
+            .starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="14">"#));
+        assert_eq!(heatmap.to_ansi(n(1)).lines().count(), 14);
+        assert_eq!(Heatmap::default().to_ansi(n(1)), "");
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        // Reads [base - 5] and writes [2^40].
+        let tape = Tape::from_str("1201,-5,0,1099511627776,99").unwrap();
+        let instruction = Instruction::decode(&tape, 0).unwrap();
+        let mut heatmap = Heatmap::default();
+        heatmap.record(&instruction, 0);
+        assert_eq!(heatmap.out_of_range(), 2);
+        assert_eq!(heatmap.len(), 4);
+        assert_eq!(heatmap.execs(0), 1);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use super::cfg::reachable;
+use super::disasm::Instruction;
+use super::{Integer, ParamMode, Tape};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    ImmediateWrite,
+    UnknownOpcode,
+    JumpOutOfRange,
+    UninitializedRead,
+    UnreachableCode,
+}
+
+impl Lint {
+    /// Stable identifier, never reused or renumbered.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::ImmediateWrite => "IC001",
+            Lint::UnknownOpcode => "IC002",
+            Lint::JumpOutOfRange => "IC003",
+            Lint::UninitializedRead => "IC004",
+            Lint::UnreachableCode => "IC005",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::ImmediateWrite => "immediate-write",
+            Lint::UnknownOpcode => "unknown-opcode",
+            Lint::JumpOutOfRange => "jump-out-of-range",
+            Lint::UninitializedRead => "uninitialized-read",
+            Lint::UnreachableCode => "unreachable-code",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub lint: Lint,
+    pub addr: Integer,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} at {}: {}",
+            self.lint.code(),
+            self.lint.name(),
+            self.addr,
+            self.message
+        )
+    }
+}
+
+pub fn lint(tape: &Tape) -> Vec<Finding> {
+    let len = tape.memory().len() as Integer;
+    let (instructions, invalid) = reachable(tape);
+    let mut findings = Vec::new();
+
+    for addr in invalid {
+        findings.push(Finding {
+            lint: Lint::UnknownOpcode,
+            addr,
+            message: format!("cannot decode {}", tape.get(addr)),
+        });
+    }
+
+    let written: HashSet<Integer> = instructions
+        .values()
+        .filter_map(Instruction::dst)
+        .filter(|dst| dst.mode == ParamMode::Position)
+        .map(|dst| dst.value)
+        .collect();
+
+    for instruction in instructions.values() {
+        if let Some(dst) = instruction.dst() {
+            if dst.mode == ParamMode::Immediate {
+                findings.push(Finding {
+                    lint: Lint::ImmediateWrite,
+                    addr: instruction.addr,
+                    message: format!(
+                        "`{}` writes to immediate operand {}, treated as [{}]",
+                        instruction.opcode.mnemonic(),
+                        dst.value,
+                        dst.value
+                    ),
+                });
+            }
+        }
+
+        if let Some(target) = instruction.jump_target() {
+            if instruction.may_jump()
+                && target.mode == ParamMode::Immediate
+                && !(0..len).contains(&target.value)
+            {
+                findings.push(Finding {
+                    lint: Lint::JumpOutOfRange,
+                    addr: instruction.addr,
+                    message: format!("jump target {} outside 0..{len}", target.value),
+                });
+            }
+        }
+
+        for src in instruction.srcs() {
+            if src.mode == ParamMode::Position && src.value >= len && !written.contains(&src.value)
+            {
+                findings.push(Finding {
+                    lint: Lint::UninitializedRead,
+                    addr: instruction.addr,
+                    message: format!("reads [{}] which is never written", src.value),
+                });
+            }
+        }
+    }
+
+    let mut covered = HashSet::new();
+    let mut referenced = HashSet::new();
+    for instruction in instructions.values() {
+        covered.extend(instruction.addr..instruction.next());
+        referenced.extend(
+            instruction
+                .params
+                .iter()
+                .filter(|param| param.mode != ParamMode::Relative)
+                .map(|param| param.value),
+        );
+    }
+
+    let mut addr = 0;
+    while addr < len {
+        if covered.contains(&addr) {
+            addr += 1;
+            continue;
+        }
+        let start = addr;
+        while addr < len && !covered.contains(&addr) {
+            addr += 1;
+        }
+        let end = addr;
+        let is_data = (start..end).any(|addr| referenced.contains(&addr));
+        if !is_data && Instruction::decode(tape, start).is_some() {
+            findings.push(Finding {
+                lint: Lint::UnreachableCode,
+                addr: start,
+                message: format!("{} cells at {start}..{end} are never executed", end - start),
+            });
+        }
+    }
+
+    findings.sort_by_key(|finding| (finding.addr, finding.lint));
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    fn lints(tape: &str) -> Vec<(Lint, Integer)> {
+        let tape = Tape::from_str(tape).unwrap();
+        lint(&tape).into_iter().map(|f| (f.lint, f.addr)).collect()
+    }
+
+    #[test]
+    fn test_clean() {
+        assert_eq!(lints("3,9,8,9,10,9,4,9,99,-1,8"), vec![]);
+    }
+
+    #[test]
+    fn test_immediate_write() {
+        assert_eq!(lints("11101,1,2,3,99"), vec![(Lint::ImmediateWrite, 0)]);
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        assert_eq!(lints("1105,1,3,42"), vec![(Lint::UnknownOpcode, 3)]);
+    }
+
+    #[test]
+    fn test_jump_out_of_range() {
+        assert_eq!(
+            lints("1105,1,100,99"),
+            vec![(Lint::JumpOutOfRange, 0), (Lint::UnreachableCode, 3)]
+        );
+    }
+
+    #[test]
+    fn test_uninitialized_read() {
+        assert_eq!(lints("4,1000,99"), vec![(Lint::UninitializedRead, 0)]);
+        assert_eq!(lints("1101,1,2,1000,4,1000,99"), vec![]);
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        assert_eq!(
+            lints("1105,1,7,104,1,99,0,99"),
+            vec![(Lint::UnreachableCode, 3)]
+        );
+    }
+}
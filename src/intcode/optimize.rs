@@ -0,0 +1,613 @@
+use std::collections::{HashMap, HashSet};
+
+use super::cfg::{reachable, Cfg};
+use super::disasm::{disassemble, Instruction, Line, Param};
+use super::{Integer, OpCode, ParamMode, Tape};
+
+/// Upper bound on rewrite rounds; every round only runs if the previous one
+/// changed something.
+const MAX_ROUNDS: usize = 32;
+
+/// What the rewrites may rely on about the program. Cells that are ever
+/// written, or read as data, are never rewritten.
+struct Analysis {
+    written: HashSet<Integer>,
+    read: HashSet<Integer>,
+}
+
+// The instructions the program may execute: the reachable ones, and when a
+// dynamic jump makes that set uncertain, every instruction found by a linear
+// sweep as well.
+fn instructions(tape: &Tape) -> Vec<Instruction> {
+    let (instructions, _) = reachable(tape);
+    let dynamic_jump = instructions.values().any(|instruction| {
+        instruction.may_jump() && instruction.jump_target().unwrap().mode != ParamMode::Immediate
+    });
+    let swept = if dynamic_jump {
+        disassemble(tape)
+    } else {
+        Vec::new()
+    };
+    let swept = swept.into_iter().filter_map(|line| match line {
+        Line::Instruction(instruction) => Some(instruction),
+        Line::Data(..) => None,
+    });
+    instructions.into_values().chain(swept).collect()
+}
+
+/// Whether a relative-mode access may land in the program image. Within a
+/// function `relbase` is followed relative to its value on entry, assuming
+/// that calls return with it restored, just as the `Cfg` assumes they return
+/// at all. The lowest value a function can be entered with then follows from
+/// its call sites. Programs that do not fit this are assumed to reach it.
+fn relative_reaches_image(tape: &Tape, instructions: &[Instruction]) -> bool {
+    let cfg = Cfg::build(tape);
+    let len = tape.memory().len() as Integer;
+    let adjustment = |instruction: &Instruction| match instruction.opcode {
+        OpCode::AdjustRelBase(ParamMode::Immediate) => Some(instruction.params[0].value),
+        OpCode::AdjustRelBase(_) => None,
+        _ => Some(0),
+    };
+
+    // Function and `relbase` offset from its entry value for every block.
+    let mut entered: HashMap<Integer, (Integer, Integer)> = HashMap::new();
+    let mut calls = Vec::new();
+    for &entry in &cfg.functions {
+        let mut worklist = vec![(entry, 0)];
+        while let Some((start, delta)) = worklist.pop() {
+            if start != entry && cfg.functions.contains(&start) {
+                continue;
+            }
+            let Some(block) = cfg.blocks.get(&start) else {
+                continue;
+            };
+            match entered.insert(start, (entry, delta)) {
+                Some(known) if known == (entry, delta) => continue,
+                Some(_) => return true,
+                None => {}
+            }
+            let mut end = delta;
+            for instruction in &block.instructions {
+                let Some(delta) = adjustment(instruction).and_then(|adj| end.checked_add(adj))
+                else {
+                    return true;
+                };
+                end = delta;
+            }
+            let last = block.last();
+            let dynamic_jump =
+                last.may_jump() && last.jump_target().unwrap().mode != ParamMode::Immediate;
+            match block.call {
+                // Frames only grow towards higher addresses.
+                Some(_) if end < 0 => return true,
+                Some(callee) => calls.push((entry, end, callee)),
+                // Returns must restore the caller's `relbase`.
+                None if dynamic_jump && end != 0 => return true,
+                None => {}
+            }
+            worklist.extend(block.succs.iter().map(|&succ| (succ, end)));
+        }
+    }
+
+    let mut lowest: HashMap<Integer, Integer> = HashMap::from([(0, 0)]);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(caller, delta, callee) in &calls {
+            let Some(&low) = lowest.get(&caller) else {
+                continue;
+            };
+            let Some(low) = low.checked_add(delta) else {
+                return true;
+            };
+            if lowest.get(&callee).is_none_or(|&known| low < known) {
+                lowest.insert(callee, low);
+                changed = true;
+            }
+        }
+    }
+
+    let reaches = |instruction: &Instruction, rb: Integer| {
+        instruction.params.iter().any(|param| {
+            param.mode == ParamMode::Relative
+                && rb.checked_add(param.value).is_none_or(|addr| addr < len)
+        })
+    };
+    let mut covered = HashSet::new();
+    // Lowest `relbase` at a jump the `Cfg` cannot follow.
+    let mut escape = None;
+    for (start, &(entry, delta)) in &entered {
+        let Some(mut rb) = lowest.get(&entry).and_then(|low| low.checked_add(delta)) else {
+            return true;
+        };
+        for instruction in &cfg.blocks[start].instructions {
+            covered.insert(instruction.addr);
+            if reaches(instruction, rb) {
+                return true;
+            }
+            let dynamic_jump = instruction.may_jump()
+                && instruction.jump_target().unwrap().mode != ParamMode::Immediate;
+            if dynamic_jump && escape.is_none_or(|low| rb < low) {
+                escape = Some(rb);
+            }
+            let Some(next) = adjustment(instruction).and_then(|adj| rb.checked_add(adj)) else {
+                return true;
+            };
+            rb = next;
+        }
+    }
+    // Code found only by the linear sweep can only be entered through such a
+    // jump, and keeps the `relbase` it had there unless it adjusts it.
+    instructions
+        .iter()
+        .filter(|instruction| !covered.contains(&instruction.addr))
+        .any(|instruction| {
+            let relative = instruction
+                .params
+                .iter()
+                .any(|param| param.mode == ParamMode::Relative);
+            matches!(instruction.opcode, OpCode::AdjustRelBase(..))
+                || relative && escape.is_none_or(|low| reaches(instruction, low))
+        })
+}
+
+impl Analysis {
+    fn new(tape: &Tape) -> Self {
+        let mut written = HashSet::new();
+        let mut read = HashSet::new();
+        for instruction in instructions(tape) {
+            if let Some(dst) = instruction.dst() {
+                if dst.mode != ParamMode::Relative {
+                    written.insert(dst.value);
+                }
+            }
+            for src in instruction.srcs() {
+                if src.mode == ParamMode::Position {
+                    read.insert(src.value);
+                }
+            }
+        }
+        Self { written, read }
+    }
+
+    fn is_written(&self, instruction: &Instruction) -> bool {
+        (instruction.addr..instruction.next()).any(|addr| self.written.contains(&addr))
+    }
+
+    fn is_frozen(&self, instruction: &Instruction) -> bool {
+        self.is_written(instruction)
+            || (instruction.addr..instruction.next()).any(|addr| self.read.contains(&addr))
+    }
+
+    /// Value of a program cell that nothing ever writes to. Cells past the
+    /// program image may be part of the stack.
+    fn constant(&self, tape: &Tape, addr: Integer) -> Option<Integer> {
+        let image = 0..tape.memory().len() as Integer;
+        (image.contains(&addr) && !self.written.contains(&addr)).then(|| tape.get(addr))
+    }
+}
+
+fn immediate(value: Integer) -> Param {
+    Param {
+        mode: ParamMode::Immediate,
+        value,
+    }
+}
+
+// Immediate destinations are written as if they were positional.
+fn location(param: Param) -> Param {
+    match param.mode {
+        ParamMode::Immediate => Param {
+            mode: ParamMode::Position,
+            value: param.value,
+        },
+        _ => param,
+    }
+}
+
+/// Position and relative cells can only be told apart within one mode.
+fn may_alias(a: Param, b: Param) -> bool {
+    match (a.mode, b.mode) {
+        (ParamMode::Immediate, _) | (_, ParamMode::Immediate) => false,
+        (a_mode, b_mode) if a_mode == b_mode => a.value == b.value,
+        _ => true,
+    }
+}
+
+fn encode(code: Integer, params: &[Param]) -> Vec<Integer> {
+    let modes = params.iter().rev().fold(0, |modes, param| {
+        let digit = match param.mode {
+            ParamMode::Position => 0,
+            ParamMode::Immediate => 1,
+            ParamMode::Relative => 2,
+        };
+        modes * 10 + digit
+    });
+    let mut cells = vec![modes * 100 + code];
+    cells.extend(params.iter().map(|param| param.value));
+    cells
+}
+
+fn write(tape: &mut Tape, instruction: &Instruction, cells: &[Integer]) -> bool {
+    let addrs = instruction.addr..instruction.addr + cells.len() as Integer;
+    if addrs
+        .clone()
+        .zip(cells)
+        .all(|(addr, &cell)| tape.get(addr) == cell)
+    {
+        return false;
+    }
+    for (addr, &cell) in addrs.zip(cells) {
+        tape.set(addr, cell);
+    }
+    true
+}
+
+/// Known contents of cells within a basic block, either a constant or a copy
+/// of another cell that has not changed since.
+#[derive(Default)]
+struct Facts(HashMap<(ParamMode, Integer), Param>);
+
+impl Facts {
+    fn resolve(&self, tape: &Tape, analysis: &Analysis, param: Param) -> Param {
+        if param.mode == ParamMode::Position {
+            if let Some(value) = analysis.constant(tape, param.value) {
+                return immediate(value);
+            }
+        }
+        self.0
+            .get(&(param.mode, param.value))
+            .copied()
+            .unwrap_or(param)
+    }
+
+    fn kill(&mut self, dst: Param) {
+        self.0.retain(|&(mode, value), known| {
+            !may_alias(Param { mode, value }, dst) && !may_alias(*known, dst)
+        });
+    }
+
+    fn kill_relative(&mut self) {
+        self.0.retain(|&(mode, _), known| {
+            mode != ParamMode::Relative && known.mode != ParamMode::Relative
+        });
+    }
+}
+
+/// Substitutes known cells into source operands, folds arithmetic on
+/// immediates into a plain `add v, 0, dst` store and turns `mul x, 1` and
+/// `add 0, x` copies into `add x, 0`.
+fn propagate(tape: &mut Tape, analysis: &Analysis) -> bool {
+    let mut changed = false;
+    for block in Cfg::build(tape).blocks.values() {
+        let mut facts = Facts::default();
+        for instruction in &block.instructions {
+            if analysis.is_written(instruction) {
+                facts = Facts::default();
+                continue;
+            }
+
+            let code = tape.get(instruction.addr) % 100;
+            let dst_index = instruction.opcode.dst_index();
+            let mut params = instruction.params.clone();
+            for (i, param) in params.iter_mut().enumerate() {
+                if Some(i) != dst_index {
+                    *param = facts.resolve(tape, analysis, *param);
+                }
+            }
+
+            let (code, params) = match (instruction.opcode, params.as_slice()) {
+                (OpCode::Add(..) | OpCode::Mul(..), &[lhs, rhs, dst]) => {
+                    let value = match (instruction.opcode, lhs.mode, rhs.mode) {
+                        // Products and sums that overflow are left unfolded.
+                        (OpCode::Add(..), ParamMode::Immediate, ParamMode::Immediate) => {
+                            lhs.value.checked_add(rhs.value).map(immediate)
+                        }
+                        (OpCode::Mul(..), ParamMode::Immediate, ParamMode::Immediate) => {
+                            lhs.value.checked_mul(rhs.value).map(immediate)
+                        }
+                        (OpCode::Add(..), _, _) if rhs == immediate(0) => Some(lhs),
+                        (OpCode::Add(..), _, _) if lhs == immediate(0) => Some(rhs),
+                        (OpCode::Mul(..), _, _) if rhs == immediate(1) => Some(lhs),
+                        (OpCode::Mul(..), _, _) if lhs == immediate(1) => Some(rhs),
+                        _ => None,
+                    };
+                    match value {
+                        Some(value) => (1, vec![value, immediate(0), dst]),
+                        None => (code, vec![lhs, rhs, dst]),
+                    }
+                }
+                (OpCode::LessThan(..) | OpCode::Equals(..), &[lhs, rhs, dst])
+                    if lhs.mode == ParamMode::Immediate && rhs.mode == ParamMode::Immediate =>
+                {
+                    let value = match instruction.opcode {
+                        OpCode::LessThan(..) => lhs.value < rhs.value,
+                        _ => lhs.value == rhs.value,
+                    };
+                    (1, vec![immediate(value as Integer), immediate(0), dst])
+                }
+                _ => (code, params),
+            };
+
+            match instruction.opcode {
+                OpCode::AdjustRelBase(..) => facts.kill_relative(),
+                OpCode::Input(..) => facts.kill(location(params[0])),
+                OpCode::Add(..) | OpCode::Mul(..) | OpCode::LessThan(..) | OpCode::Equals(..) => {
+                    let dst = location(params[2]);
+                    facts.kill(dst);
+                    if code == 1 && params[1] == immediate(0) && !may_alias(params[0], dst) {
+                        facts.0.insert((dst.mode, dst.value), params[0]);
+                    }
+                }
+                _ => {}
+            }
+
+            if !analysis.is_frozen(instruction) {
+                changed |= write(tape, instruction, &encode(code, &params));
+            }
+        }
+    }
+    changed
+}
+
+fn unconditional(instruction: &Instruction) -> bool {
+    instruction.may_jump() && !instruction.falls_through()
+}
+
+/// Points jumps that land on an unconditional jump straight at its target,
+/// and replaces unconditional jumps to `halt` with `halt` itself.
+fn thread(tape: &mut Tape, analysis: &Analysis) -> bool {
+    let (instructions, _) = reachable(tape);
+    let stable = |addr: &Integer| {
+        instructions
+            .get(addr)
+            .filter(|instruction| !analysis.is_written(instruction))
+    };
+
+    let mut changed = false;
+    for jump in instructions.values() {
+        let Some(mut target) = jump.jump_target() else {
+            continue;
+        };
+        if !jump.may_jump() || analysis.is_frozen(jump) {
+            continue;
+        }
+
+        let mut seen = HashSet::from([jump.addr]);
+        while target.mode == ParamMode::Immediate && seen.insert(target.value) {
+            match stable(&target.value) {
+                Some(next) if unconditional(next) => target = next.jump_target().unwrap(),
+                _ => break,
+            }
+        }
+
+        let halts = target.mode == ParamMode::Immediate
+            && stable(&target.value).is_some_and(|next| next.opcode == OpCode::Eof);
+        let cells = if unconditional(jump) && halts {
+            vec![99]
+        } else {
+            let code = tape.get(jump.addr) % 100;
+            encode(code, &[jump.params[0], target])
+        };
+        changed |= write(tape, jump, &cells);
+    }
+    changed
+}
+
+/// Rewrites the program into an equivalent one that executes no more
+/// instructions than the original and reads fewer cells.
+///
+/// Instructions are only rewritten in place, so every address keeps its
+/// meaning. Cells that any instruction may write, or read as data, are left
+/// alone, which keeps self-modifying code intact. Relative-mode accesses
+/// must address a stack beyond the program image, as they do in compiled
+/// programs. A program where they may reach the image is returned
+/// unchanged.
+pub fn optimize(tape: &Tape) -> Tape {
+    let mut tape = Tape::from(tape.memory().to_vec());
+    // Rewrites only reuse existing operands, so this holds in every round.
+    if relative_reaches_image(&tape, &instructions(&tape)) {
+        return tape;
+    }
+    for _ in 0..MAX_ROUNDS {
+        let analysis = Analysis::new(&tape);
+        let mut changed = propagate(&mut tape, &analysis);
+        let analysis = Analysis::new(&tape);
+        changed |= thread(&mut tape, &analysis);
+        if !changed {
+            break;
+        }
+    }
+    tape
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::intcode::compiler::compile;
+    use crate::intcode::{Io, RunStatus};
+
+    /// Runs to completion, returning the outputs and the number of executed
+    /// instructions.
+    fn execute(tape: &Tape, input: &[Integer]) -> (Vec<Integer>, usize) {
+        let mut tape = tape.fork();
+        let mut input = input.iter().copied();
+        let mut output = Vec::new();
+        let mut steps = 0;
+        loop {
+            let status = tape.run_limited(1, |io| match io {
+                Io::Input => input.next(),
+                Io::Output(value) => {
+                    output.push(value);
+                    None
+                }
+            });
+            match status {
+                RunStatus::StepLimit { .. } => steps += 1,
+                RunStatus::Halt { .. } => return (output, steps + 1),
+                status => panic!("unexpected {status:?}"),
+            }
+        }
+    }
+
+    // Cheap deterministic input generator, so the equivalence checks cover
+    // many paths without pulling in a random number crate.
+    fn inputs(seed: u64, len: usize) -> Vec<Integer> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                ((state >> 33) % 21) as Integer - 10
+            })
+            .collect()
+    }
+
+    fn assert_equivalent(tape: &Tape, runs: &[Vec<Integer>]) -> (usize, usize) {
+        let optimized = optimize(tape);
+        let (mut before, mut after) = (0, 0);
+        for input in runs {
+            let (expected, steps) = execute(tape, input);
+            let (actual, optimized_steps) = execute(&optimized, input);
+            assert_eq!(actual, expected, "input {input:?}");
+            assert!(optimized_steps <= steps, "input {input:?}");
+            before += steps;
+            after += optimized_steps;
+        }
+        (before, after)
+    }
+
+    #[test]
+    fn test_compiled_programs() {
+        const SOURCES: [&str; 3] = [
+            r#"
+                fn main() {
+                    output(2 * (3 + 4) - 1);
+                    let n = input();
+                    while n != 0 {
+                        if n < 0 {
+                            output(-1);
+                        } else if n == 0 {
+                            output(0);
+                        } else {
+                            output(n * 1 + 0);
+                        }
+                        n = input();
+                    }
+                }
+            "#,
+            r#"
+                fn fib(n) {
+                    if n < 2 {
+                        return n;
+                    }
+                    return fib(n - 1) + fib(n - 2);
+                }
+                fn main() {
+                    let n = input();
+                    if n < 0 { n = -n; }
+                    output(fib(n));
+                }
+            "#,
+            r#"
+                fn max(a, b) { if a > b { return a; } else { return b; } }
+                fn main() {
+                    let best = input();
+                    let i = 0;
+                    while i < 8 {
+                        i = i + 1;
+                        let x = input();
+                        if x != 0 {
+                            if x > 0 {
+                                best = max(best, x);
+                            } else {
+                                output(x);
+                            }
+                        } else {
+                            output(best);
+                        }
+                    }
+                    output(best);
+                }
+            "#,
+        ];
+
+        let mut saved = false;
+        for source in SOURCES {
+            let tape = Tape::from(compile(source).unwrap());
+            let runs: Vec<_> = (0..32)
+                .map(|seed| {
+                    let mut input = inputs(seed, 9);
+                    // Keep the loops bounded.
+                    input.push(0);
+                    input
+                })
+                .collect();
+            let (before, after) = assert_equivalent(&tape, &runs);
+            saved |= after < before;
+        }
+        assert!(saved);
+    }
+
+    #[test]
+    fn test_rewrites() {
+        // mul 3, 4, [20]; jnz 1, 10; halt; out [20]; jnz 1, 15; jnz 1, 8
+        let tape =
+            Tape::from_str("1102,3,4,20,1105,1,10,99,99,99,4,20,1105,1,15,1105,1,8,0,0,0").unwrap();
+        let optimized = optimize(&tape);
+        let listing: Vec<String> = disassemble(&optimized)
+            .iter()
+            .take(7)
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            listing,
+            vec![
+                "     0: add  12, 0, [20]",
+                "     4: jnz  1, 10",
+                "     7: halt",
+                "     8: halt",
+                "     9: halt",
+                "    10: out  [20]",
+                "    12: halt",
+            ]
+        );
+        assert_eq!(execute(&optimized, &[]), (vec![12], 4));
+        assert_eq!(execute(&tape, &[]), (vec![12], 6));
+    }
+
+    #[test]
+    fn test_overflow_is_not_folded() {
+        // mul 9223372036854775807, 2, [5]; halt
+        let tape = Tape::from_str("1102,9223372036854775807,2,5,99,0").unwrap();
+        assert_eq!(optimize(&tape).memory().to_vec(), tape.memory().to_vec());
+    }
+
+    #[test]
+    fn test_relative_writes_into_the_image() {
+        // With relbase 0, `add 5, 0, [rb+7]` overwrites the 3 that the `mul`
+        // at 6 reads, so [20] ends up 5 * 4 rather than 3 * 4.
+        const TAPE: &str = "109,0,21101,5,0,7,1102,3,4,20,4,20,99,0,0,0,0,0,0,0,0";
+        let tape = Tape::from_str(TAPE).unwrap();
+        assert_eq!(execute(&tape, &[]).0, vec![20]);
+        assert_equivalent(&tape, &[vec![]]);
+    }
+
+    #[test]
+    fn test_self_modifying_code_is_untouched() {
+        // Stores 1101 over the `add` at 4 before running it.
+        const TAPE: &str = "1101,1101,0,4,1,5,6,11,4,11,99,0";
+        let tape = Tape::from_str(TAPE).unwrap();
+        assert_eq!(optimize(&tape).memory().to_vec(), tape.memory().to_vec());
+        assert_equivalent(&tape, &[vec![]]);
+
+        // The day09 quine reads its own code through the relative base.
+        const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let quine = Tape::from_str(QUINE).unwrap();
+        assert_equivalent(&quine, &[vec![]]);
+    }
+}
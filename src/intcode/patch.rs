@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{Integer, Tape};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PatchError {
+    #[error("invalid patch entry `{0}`, expected <addr>=<value>")]
+    Entry(String),
+    #[error("cannot read patch file {0}")]
+    File(String),
+    #[error("input is not an Intcode program")]
+    NotIntcode,
+    #[error("address {0} is too far past the end of the program")]
+    Address(Integer),
+}
+
+/// How far past the end of a program a patch may write. Programs keep their
+/// scratch memory close behind their code, and the bound keeps a mistyped
+/// address from allocating gigabytes.
+pub const MAX_GROWTH: usize = 4096;
+
+/// Memory writes applied to a program before it runs, written as
+/// `addr=value` entries separated by commas or newlines, e.g. `1=12,2=2`.
+/// In patch files everything after a `#` is a comment.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Patch {
+    writes: Vec<(Integer, Integer)>,
+}
+
+impl FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let writes = s
+            .lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(|line| line.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let parse = || {
+                    let (addr, value) = entry.split_once('=')?;
+                    let addr: Integer = addr.trim().parse().ok()?;
+                    let value = value.trim().parse().ok()?;
+                    (addr >= 0).then_some((addr, value))
+                };
+                parse().ok_or_else(|| PatchError::Entry(entry.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { writes })
+    }
+}
+
+impl Patch {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PatchError> {
+        let path = path.as_ref();
+        let content =
+            fs::read_to_string(path).map_err(|_| PatchError::File(path.display().to_string()))?;
+        content.parse()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Writes the patch into `tape` and marks the addresses as patched, so
+    /// that `Tape::set_unpatched` leaves them alone.
+    pub fn apply(&self, tape: &mut Tape) -> Result<(), PatchError> {
+        let limit = tape.memory().len() + MAX_GROWTH;
+        if let Some(&(addr, _)) = self
+            .writes
+            .iter()
+            .find(|&&(addr, _)| addr as usize >= limit)
+        {
+            return Err(PatchError::Address(addr));
+        }
+        for &(addr, value) in &self.writes {
+            tape.set(addr, value);
+            tape.patched.push(addr);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let patch = Patch::from_str("1=12, 2=2\n# quarters\n0 = 2 # free play\n").unwrap();
+        assert_eq!(patch.writes, vec![(1, 12), (2, 2), (0, 2)]);
+
+        assert_eq!(
+            Patch::from_str("1=12,2"),
+            Err(PatchError::Entry("2".into()))
+        );
+        assert_eq!(
+            Patch::from_str("-1=0"),
+            Err(PatchError::Entry("-1=0".into()))
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let patch = Patch::from_str("1=12,2=2,6=-1").unwrap();
+        let mut tape = Tape::from_str("1,0,0,0,99").unwrap();
+        assert_eq!(patch.apply(&mut tape), Ok(()));
+        assert_eq!(tape.memory().to_vec(), vec![1, 12, 2, 0, 99, 0, -1]);
+
+        tape.set_unpatched(1, 5);
+        tape.set_unpatched(3, 5);
+        assert_eq!(tape.memory().to_vec(), vec![1, 12, 2, 5, 99, 0, -1]);
+
+        let far = Patch::from_str("999999999999=1").unwrap();
+        assert_eq!(far.apply(&mut tape), Err(PatchError::Address(999999999999)));
+        assert_eq!(tape.memory().len(), 7);
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+
+use super::{Integer, Tape};
+
+/// A machine snapshot together with the caller's view of the world and the
+/// inputs that led there.
+#[derive(Clone, Debug)]
+pub struct Node<S> {
+    pub tape: Tape,
+    pub state: S,
+    pub path: Vec<Integer>,
+}
+
+/// Breadth-first search over forked machines.
+///
+/// Every node is expanded by forking its machine once per value in `inputs`
+/// and running it until it polls again. `step` turns the previous state, the
+/// fed input and the produced outputs into the next state, or `None` to prune
+/// the branch. Nodes whose `key` has been seen before are dropped. The first
+/// node for which `goal` holds is returned, so its path is a shortest one.
+pub fn bfs<S, K, KF, SF, GF>(
+    tape: &Tape,
+    state: S,
+    inputs: &[Integer],
+    key: KF,
+    mut step: SF,
+    goal: GF,
+) -> Option<Node<S>>
+where
+    K: Hash + Eq,
+    KF: Fn(&S) -> K,
+    SF: FnMut(&S, Integer, &[Integer]) -> Option<S>,
+    GF: Fn(&S) -> bool,
+{
+    let root = Node {
+        tape: tape.fork(),
+        state,
+        path: Vec::new(),
+    };
+    if goal(&root.state) {
+        return Some(root);
+    }
+
+    let mut seen = HashSet::from([key(&root.state)]);
+    let mut queue = VecDeque::from([root]);
+
+    while let Some(node) = queue.pop_front() {
+        for &input in inputs {
+            let mut tape = node.tape.fork();
+            let (status, output) = tape.run_with(&[input]);
+
+            let Some(state) = step(&node.state, input, &output) else {
+                continue;
+            };
+            if !seen.insert(key(&state)) {
+                continue;
+            }
+
+            let mut path = node.path.clone();
+            path.push(input);
+            let child = Node { tape, state, path };
+
+            if goal(&child.state) {
+                return Some(child);
+            }
+            if status.is_poll() {
+                queue.push_back(child);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    // Reads a value, adds it to an accumulator and outputs the sum, forever.
+    const ACCUMULATOR: &str = "3,100,1,100,101,101,4,101,1105,1,0";
+
+    #[test]
+    fn test_shortest_path() {
+        let tape = Tape::from_str(ACCUMULATOR).unwrap();
+
+        let found = bfs(
+            &tape,
+            0,
+            &[3, 4],
+            |&acc| acc,
+            |_, _, output| output.last().copied().filter(|&acc| acc <= 10),
+            |&acc| acc == 10,
+        )
+        .unwrap();
+
+        assert_eq!(found.path, vec![3, 3, 4]);
+        assert_eq!(found.tape.get(101), 10);
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        let tape = Tape::from_str(ACCUMULATOR).unwrap();
+
+        let found = bfs(
+            &tape,
+            0,
+            &[2],
+            |&acc| acc,
+            |_, _, output| output.last().copied().filter(|&acc| acc <= 10),
+            |&acc| acc == 7,
+        );
+
+        assert!(found.is_none());
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use super::Integer;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    Input(Integer),
+    Output(Integer),
+    /// The machine asked for input and its driver had none yet.
+    Poll,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Input(value) => write!(f, "in {value}"),
+            Event::Output(value) => write!(f, "out {value}"),
+            Event::Poll => write!(f, "poll"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TranscriptError {
+    #[error("line {0}: expected `in <value>`, `out <value>` or `poll`")]
+    Event(usize),
+    #[error("cannot access transcript {0}")]
+    File(String),
+}
+
+/// Every value read and written by Intcode machines, in order. Stored as
+/// text with one `in <value>`, `out <value>` or `poll` event per line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Transcript {
+    pub events: Vec<Event>,
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{event}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Transcript {
+    type Err = TranscriptError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let events = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(line, content)| {
+                let event = match content.trim().split_once(' ') {
+                    _ if content.trim() == "poll" => Ok(Event::Poll),
+                    Some(("in", value)) => value.trim().parse().map(Event::Input),
+                    Some(("out", value)) => value.trim().parse().map(Event::Output),
+                    _ => return Err(TranscriptError::Event(line + 1)),
+                };
+                event.map_err(|_| TranscriptError::Event(line + 1))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { events })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub left: Option<Event>,
+    pub right: Option<Event>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |event: Option<Event>| event.map_or("<end>".to_string(), |e| e.to_string());
+        write!(
+            f,
+            "event {}: {} != {}",
+            self.index + 1,
+            show(self.left),
+            show(self.right)
+        )
+    }
+}
+
+impl Transcript {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TranscriptError> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map_err(|_| TranscriptError::File(path.display().to_string()))?
+            .parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TranscriptError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_string())
+            .map_err(|_| TranscriptError::File(path.display().to_string()))
+    }
+
+    /// First event where the two transcripts differ, if any.
+    pub fn diff(&self, other: &Transcript) -> Option<Divergence> {
+        let len = self.events.len().max(other.events.len());
+        (0..len)
+            .map(|index| Divergence {
+                index,
+                left: self.events.get(index).copied(),
+                right: other.events.get(index).copied(),
+            })
+            .find(|divergence| divergence.left != divergence.right)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    transcript: Transcript,
+    replay: Vec<Option<Integer>>,
+    cursor: usize,
+}
+
+/// Records the I/O of the machines it is attached to with `Tape::record`,
+/// optionally answering their inputs from an earlier transcript. Clones are
+/// handles to the same recording, so machines forked from a recorded one are
+/// recorded too.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder(Arc<Mutex<Session>>);
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Like `new`, but inputs and polls are taken from `transcript` without
+    /// asking the machines' drivers. Once it is exhausted the drivers answer
+    /// again.
+    pub fn replay(transcript: &Transcript) -> Self {
+        let replay = transcript
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Input(value) => Some(Some(*value)),
+                Event::Poll => Some(None),
+                Event::Output(_) => None,
+            })
+            .collect();
+        Self(Arc::new(Mutex::new(Session {
+            replay,
+            ..Session::default()
+        })))
+    }
+
+    /// Everything recorded so far.
+    pub fn transcript(&self) -> Transcript {
+        self.0.lock().unwrap().transcript.clone()
+    }
+
+    pub(super) fn input(&self, live: impl FnOnce() -> Option<Integer>) -> Option<Integer> {
+        let replayed = {
+            let mut session = self.0.lock().unwrap();
+            let replayed = session.replay.get(session.cursor).copied();
+            session.cursor += replayed.is_some() as usize;
+            replayed
+        };
+        // The driver may take a while, so it runs without holding the lock.
+        let value = replayed.unwrap_or_else(live);
+        let event = value.map_or(Event::Poll, Event::Input);
+        self.0.lock().unwrap().transcript.events.push(event);
+        value
+    }
+
+    pub(super) fn output(&self, value: Integer) {
+        let event = Event::Output(value);
+        self.0.lock().unwrap().transcript.events.push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::intcode::compiler::compile;
+    use crate::intcode::{Io, Tape};
+
+    // Echoes the running sum of its inputs until it reads a zero.
+    const SUMMER: &str = r#"
+        fn main() {
+            let sum = 0;
+            let value = input();
+            while value != 0 {
+                sum = sum + value;
+                output(sum);
+                value = input();
+            }
+        }
+    "#;
+
+    const GOLDEN: &str = "in 3\nout 3\nin 4\nout 7\nin 0\n";
+
+    fn play(tape: &Tape, mut answer: impl FnMut(Option<Integer>) -> Integer) -> Vec<Integer> {
+        let mut tape = tape.fork();
+        let mut last = None;
+        let mut output = Vec::new();
+        let status = tape.run(|io| match io {
+            Io::Input => Some(answer(last)),
+            Io::Output(value) => {
+                last = Some(value);
+                output.push(value);
+                None
+            }
+        });
+        assert!(status.is_halt());
+        output
+    }
+
+    #[test]
+    fn test_record() {
+        let mut tape = Tape::from(compile(SUMMER).unwrap());
+        let recorder = Recorder::new();
+        tape.record(recorder.clone());
+
+        // Forks are recorded too, even on other threads.
+        let fork = tape.fork();
+        std::thread::spawn(move || {
+            let mut inputs = [3, 4, 0].into_iter();
+            play(&fork, |_| inputs.next().unwrap());
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(recorder.transcript().to_string(), GOLDEN);
+    }
+
+    #[test]
+    fn test_poll() {
+        let mut tape = Tape::from(compile(SUMMER).unwrap());
+        let recorder = Recorder::new();
+        tape.record(recorder.clone());
+
+        assert!(!tape.run(|_| None).is_halt());
+        let (status, _) = tape.run_with(&[0]);
+        assert!(status.is_halt());
+
+        let transcript = recorder.transcript();
+        assert_eq!(transcript.to_string(), "poll\nin 0\n");
+        assert_eq!(
+            Transcript::from_str(&transcript.to_string()),
+            Ok(transcript)
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut tape = Tape::from(compile(SUMMER).unwrap());
+        let golden = Transcript::from_str(GOLDEN).unwrap();
+        let recorder = Recorder::replay(&golden);
+        tape.record(recorder.clone());
+
+        // The transcript answers every input, the driver is never asked.
+        let output = play(&tape, |_| unreachable!());
+
+        assert_eq!(output, vec![3, 7]);
+        assert_eq!(golden.diff(&recorder.transcript()), None);
+    }
+
+    #[test]
+    fn test_diff() {
+        let golden = Transcript::from_str(GOLDEN).unwrap();
+        let other = Transcript::from_str("in 3\nout 3\nin 5\nout 8\nin 0\n").unwrap();
+
+        let divergence = golden.diff(&other).unwrap();
+        assert_eq!(divergence.index, 2);
+        assert_eq!(divergence.to_string(), "event 3: in 4 != in 5");
+
+        let shorter = Transcript::from_str("in 3\nout 3\n").unwrap();
+        assert_eq!(
+            golden.diff(&shorter).unwrap().to_string(),
+            "event 3: in 4 != <end>"
+        );
+
+        assert_eq!(
+            Transcript::from_str("in 3\nfoo\n"),
+            Err(TranscriptError::Event(2))
+        );
+    }
+}
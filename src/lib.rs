@@ -0,0 +1,74 @@
+//! Solutions to Advent of Code 2019 and the Intcode machine they share.
+//!
+//! Each `dayNN` module exposes its puzzle `Input` (parsed with `FromStr`)
+//! and the `part1` and `part2` solvers, and registers itself as a
+//! `solution::Day` through its `SOLUTION` constant, which `registry`
+//! collects.
+//!
+//! ```
+//! use advent_of_code_2019::{day01, registry};
+//!
+//! let input: day01::Input = "12\n14\n".parse().unwrap();
+//! assert_eq!(day01::part1(&input), 4);
+//!
+//! let registry = registry();
+//! let answers = registry.get(1).unwrap().solve("1969\n", None).unwrap();
+//! assert_eq!(answers.part2.as_deref(), Some("966"));
+//! ```
+
+use answers::AnswersError;
+use bench::BaselineError;
+use intcode::patch::PatchError;
+use intcode::transcript::TranscriptError;
+use solution::{ParseError, Registry, SolveError};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("logic error: {0}")]
+    Logic(String),
+    #[error("patch error: {0}")]
+    Patch(#[from] PatchError),
+    #[error("transcript error: {0}")]
+    Transcript(#[from] TranscriptError),
+    #[error("answers error: {0}")]
+    Answers(#[from] AnswersError),
+    #[error("bench error: {0}")]
+    Baseline(#[from] BaselineError),
+    #[error("parse error: {0}")]
+    Parse(#[from] ParseError),
+    #[error("{0}")]
+    Solve(#[from] SolveError),
+    #[error("missing input file {0}")]
+    MissingInput(String),
+    #[error("panicked: {0}")]
+    Panic(String),
+}
+
+pub mod answers;
+pub mod bench;
+pub mod input;
+pub mod intcode;
+pub mod json;
+pub mod solution;
+
+/// Declares the day modules and builds `registry` from the `SOLUTION` each
+/// of them exposes.
+macro_rules! days {
+    ($($day:ident),* $(,)?) => {
+        $(pub mod $day;)*
+
+        /// Every implemented day.
+        pub fn registry() -> Registry {
+            let mut registry = Registry::default();
+            $(registry.register($day::SOLUTION);)*
+            registry
+        }
+    };
+}
+
+days!(
+    day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12, day13,
+    day14,
+);
@@ -1,63 +1,137 @@
-use std::fs::File;
-use std::io::Read;
+use advent_of_code_2019::input::Input;
+use advent_of_code_2019::intcode::transcript::{Recorder, Transcript};
+use advent_of_code_2019::solution::{self, Setup};
+use advent_of_code_2019::{registry, Error};
+use cli::{Command, Format, Source};
 
-#[derive(thiserror::Error, Debug)]
-pub enum Error {
-    #[error("IO error")]
-    Io(#[from] std::io::Error),
-    #[error("logic error: {0}")]
-    Logic(String),
+mod cli;
+mod report;
+mod runner;
+
+fn main() {
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{}", err.help);
+            std::process::exit(2);
+        }
+    };
+    match execute(command) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    }
 }
 
-pub fn parse_input<I>(day: &str) -> Result<I, Error>
-where
-    for<'s> I: Sized + From<&'s str>,
-{
-    let filename = format!("input/{}.txt", day);
-    let mut file = File::open(filename)?;
-    let mut content = String::new();
-    file.read_to_string(&mut content)?;
-    Ok(content.as_str().into())
+/// Runs `f` once per input the selection expands to, with a header naming
+/// the user before each in a batch if `headers` is set. Returns whether
+/// every run succeeded.
+fn each_input(
+    input: &Input,
+    headers: bool,
+    mut f: impl FnMut(&Input) -> Result<bool, Error>,
+) -> Result<bool, Error> {
+    let batch = headers && *input == Input::AllUsers;
+    let mut ok = true;
+    for (i, input) in input.expand()?.iter().enumerate() {
+        if let (true, Some(user)) = (batch, input.user()) {
+            if i > 0 {
+                println!();
+            }
+            println!("== {user} ==");
+        }
+        ok &= f(input)?;
+    }
+    Ok(ok)
 }
 
-macro_rules! aoc_impl {
-    ($($day:ident),*) => {
-        $(mod $day;)*
+/// Runs a command. Returns whether it succeeded.
+fn execute(command: Command) -> Result<bool, Error> {
+    let registry = registry();
+    match command {
+        Command::Run(run) => {
+            let replay = run.replay.as_ref().map(Transcript::load).transpose()?;
+            let recorder = match &replay {
+                Some(replay) => Some(Recorder::replay(replay)),
+                None => run.record.is_some().then(Recorder::new),
+            };
+            let setup = Setup {
+                patch: run.patch.clone(),
+                recorder: recorder.clone(),
+            };
+            // Days solved side by side share the machine's threads.
+            solution::set_threads(solution::threads() / run.jobs);
+            let text = run.format == Format::Text;
+            let mut rows = Vec::new();
+            let ok = each_input(&run.input, text, |input| {
+                Ok(runner::run(&registry, &run, &setup, input, &mut rows))
+            })?;
+            let recorded = recorder.map(|recorder| recorder.transcript());
+            match run.format {
+                Format::Text => {}
+                Format::Json => println!("{:#}", report::json(&rows)),
+                Format::Csv => print!("{}", report::csv(&rows)),
+            }
 
-        fn run_day(day: usize) {
-            assert!((1..=25).contains(&day));
-            match format!("day{:02}", day).as_ref() {
-                $(
-                    stringify!($day) => {
-                        println!(stringify!($day));
-                        let input: $day::Input = parse_input(stringify!($day)).expect("Failed to parse input");
-                        println!(">> part1: {}", $day::part1(&input));
-                        println!(">> part2: {}", $day::part2(&input));
-                    }
-                )*
-                _ => unreachable!(),
+            if let (Some(path), Some(recorded)) = (run.record, &recorded) {
+                recorded.save(path)?;
+            }
+            if let (Some(replay), Some(recorded)) = (replay, &recorded) {
+                let divergence = replay.diff(recorded);
+                let message = match &divergence {
+                    Some(divergence) => format!("replay diverges at {divergence}"),
+                    None => "replay matches transcript".to_string(),
+                };
+                // Keep machine-readable output parseable.
+                if text {
+                    println!("{message}");
+                } else {
+                    eprintln!("{message}");
+                }
+                return Ok(ok && divergence.is_none());
             }
+            Ok(ok)
         }
-
-        fn run(day: Option<usize>) {
-            if let Some(day) = day {
-                run_day(day);
-            } else {
-                for day in 1..=25 {
-                    run_day(day);
+        Command::List => {
+            print!("{}", runner::list(&registry));
+            Ok(true)
+        }
+        Command::Bench(bench) => each_input(&bench.input, true, |input| {
+            runner::bench(&registry, &bench, input)
+        }),
+        Command::Check { days, input, save } => each_input(&input, true, |input| {
+            runner::check(&registry, &days, input, save)
+        }),
+        Command::Example { days } => Ok(runner::example(&registry, &days)),
+        Command::Disasm(disasm) => {
+            let source = match disasm.source {
+                Source::Day(day) => Input::Dir.read(day)?,
+                Source::File(path) => Input::File(path.into()).read(0)?,
+                Source::Stdin => Input::Stdin.read(0)?,
+            };
+            print!("{}", runner::disasm(&source, disasm.optimize, disasm.view)?);
+            Ok(true)
+        }
+        Command::Diff { left, right } => {
+            let left = Transcript::load(left)?;
+            let right = Transcript::load(right)?;
+            match left.diff(&right) {
+                Some(divergence) => {
+                    println!("transcripts diverge at {divergence}");
+                    Ok(false)
+                }
+                None => {
+                    println!("transcripts match");
+                    Ok(true)
                 }
             }
         }
+        Command::Help(help) => {
+            print!("{help}");
+            Ok(true)
+        }
     }
 }
-
-aoc_impl!(
-    day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11, day12, day13,
-    day14
-);
-
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    let day = args.get(1).map(|n| n.parse().unwrap());
-    run(day);
-}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use advent_of_code_2019::json::Json;
+use advent_of_code_2019::solution::Registry;
+
+use crate::runner::Status;
+
+pub const COLUMNS: [&str; 8] = [
+    "user", "day", "title", "part", "answer", "time_ns", "status", "error",
+];
+
+/// One phase of one day's run, as a machine-readable record. Days that did
+/// not run get a single row without a part.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub user: Option<String>,
+    pub day: usize,
+    pub title: Option<&'static str>,
+    pub part: Option<&'static str>,
+    pub answer: Option<String>,
+    pub time: Option<Duration>,
+    pub status: &'static str,
+    pub error: Option<String>,
+}
+
+impl Row {
+    fn values(&self) -> [Json; 8] {
+        let text = |value: Option<&str>| value.map_or(Json::Null, Json::from);
+        [
+            text(self.user.as_deref()),
+            Json::Number(self.day as f64),
+            text(self.title),
+            text(self.part),
+            text(self.answer.as_deref()),
+            self.time
+                .map_or(Json::Null, |time| Json::Number(time.as_nanos() as f64)),
+            Json::from(self.status),
+            text(self.error.as_deref()),
+        ]
+    }
+}
+
+/// Rows for the parse phase and each answered part of the solved days, and
+/// one row for each day that was not.
+pub fn rows(registry: &Registry, user: Option<&str>, statuses: &[(usize, Status)]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for (day, status) in statuses {
+        let row = Row {
+            user: user.map(String::from),
+            day: *day,
+            title: registry.get(*day).map(|solution| solution.title()),
+            part: None,
+            answer: None,
+            time: None,
+            status: "solved",
+            error: None,
+        };
+        match status {
+            Status::Solved(answers, timings) => {
+                rows.push(Row {
+                    part: Some("parse"),
+                    time: Some(timings.parse),
+                    ..row.clone()
+                });
+                let parts = [
+                    ("part1", &answers.part1, timings.part1),
+                    ("part2", &answers.part2, timings.part2),
+                ];
+                for (part, answer, time) in parts {
+                    if answer.is_some() {
+                        rows.push(Row {
+                            part: Some(part),
+                            answer: answer.clone(),
+                            time,
+                            ..row.clone()
+                        });
+                    }
+                }
+            }
+            Status::NotImplemented => rows.push(Row {
+                status: "not_implemented",
+                ..row
+            }),
+            Status::Failed(err) => rows.push(Row {
+                status: "failed",
+                error: Some(err.to_string()),
+                ..row
+            }),
+        }
+    }
+    rows
+}
+
+/// An array with one object per row, keyed by `COLUMNS`.
+pub fn json(rows: &[Row]) -> Json {
+    Json::Array(
+        rows.iter()
+            .map(|row| {
+                let entries = COLUMNS.iter().map(|column| column.to_string());
+                Json::Object(entries.zip(row.values()).collect())
+            })
+            .collect(),
+    )
+}
+
+/// Quotes fields holding separators, quotes or line breaks, as in RFC 4180.
+fn csv_field(value: &Json) -> String {
+    let field = match value {
+        Json::Null => return String::new(),
+        Json::String(s) => s.clone(),
+        value => value.to_string(),
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// A header line naming `COLUMNS`, then one line per row.
+pub fn csv(rows: &[Row]) -> String {
+    let mut out = COLUMNS.join(",") + "\n";
+    for row in rows {
+        let fields: Vec<_> = row.values().iter().map(csv_field).collect();
+        out += &fields.join(",");
+        out += "\n";
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use advent_of_code_2019::solution::{Answers, Timings};
+    use advent_of_code_2019::Error;
+
+    #[test]
+    fn test_formats() {
+        let statuses = [
+            (
+                8,
+                Status::Solved(
+                    Answers {
+                        part1: None,
+                        part2: Some("\n#..\n\"a\", b".into()),
+                    },
+                    Timings {
+                        parse: Duration::from_nanos(1500),
+                        part1: None,
+                        part2: Some(Duration::from_micros(20)),
+                    },
+                ),
+            ),
+            (9, Status::Failed(Error::MissingInput("day09.txt".into()))),
+        ];
+        let rows = rows(&Registry::default(), Some("alice"), &statuses);
+
+        assert_eq!(
+            csv(&rows),
+            "user,day,title,part,answer,time_ns,status,error\n\
+             alice,8,,parse,,1500,solved,\n\
+             alice,8,,part2,\"\n#..\n\"\"a\"\", b\",20000,solved,\n\
+             alice,9,,,,,failed,missing input file day09.txt\n"
+        );
+        assert_eq!(
+            json(&rows[1..]).to_string(),
+            r#"[{"user":"alice","day":8,"title":null,"part":"part2","answer":"\n#..\n\"a\", b","time_ns":20000,"status":"solved","error":null},{"user":"alice","day":9,"title":null,"part":null,"answer":null,"time_ns":null,"status":"failed","error":"missing input file day09.txt"}]"#
+        );
+    }
+}
@@ -0,0 +1,624 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use advent_of_code_2019::answers::AnswerFile;
+use advent_of_code_2019::bench::{self, Baseline};
+use advent_of_code_2019::input::Input;
+use advent_of_code_2019::intcode::{decompile, disasm, lint, optimize, Tape};
+use advent_of_code_2019::solution::{
+    Answers, Day, ParseError, Part, Registry, Setup, Timings, DAYS,
+};
+use advent_of_code_2019::Error;
+
+use crate::cli::{self, Format, View};
+use crate::report::{self, Row};
+
+pub enum Status {
+    Solved(Answers, Timings),
+    NotImplemented,
+    Failed(Error),
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Solved(..) => write!(f, "solved"),
+            Status::NotImplemented => write!(f, "not implemented"),
+            Status::Failed(err) => write!(f, "failed: {err}"),
+        }
+    }
+}
+
+/// Runs `f`, turning a panic into an error so that one broken day does not
+/// take the others down with it.
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown cause".to_string(),
+            },
+        };
+        Err(Error::Panic(message))
+    })
+}
+
+/// Solves one day without printing anything.
+pub fn solve_day(
+    registry: &Registry,
+    day: usize,
+    input: &Input,
+    part: Option<Part>,
+    setup: &Setup,
+) -> Status {
+    let Some(solution) = registry.get(day) else {
+        return Status::NotImplemented;
+    };
+    let answers = catch_panic(|| Ok(solution.solve_timed(&input.read(day)?, part, setup)?));
+    match answers {
+        Ok((answers, timings)) => Status::Solved(answers, timings),
+        Err(err) => Status::Failed(err),
+    }
+}
+
+/// The text report of one day: its title, answers and timings.
+pub fn report_day(registry: &Registry, day: usize, status: &Status) -> String {
+    let Some(solution) = registry.get(day) else {
+        return format!("day{day:02}: not implemented\n");
+    };
+
+    let mut out = format!("day{day:02}: {}\n", solution.title());
+    match status {
+        Status::Solved(answers, timings) => {
+            if let Some(answer) = &answers.part1 {
+                out += &format!(">> part1: {answer}\n");
+            }
+            if let Some(answer) = &answers.part2 {
+                out += &format!(">> part2: {answer}\n");
+            }
+            out += &format!(">> time: {}\n", phases(timings));
+        }
+        Status::Failed(err) => out += &format!(">> {err}\n"),
+        Status::NotImplemented => {}
+    }
+    out
+}
+
+/// Calls `f` for every day on up to `jobs` threads, and hands the results to
+/// `emit` in the order of `days`, each as soon as those before it are done.
+fn in_order<T: Send>(
+    days: &[usize],
+    jobs: usize,
+    f: impl Fn(usize) -> T + Sync,
+    mut emit: impl FnMut(usize, T),
+) {
+    if jobs <= 1 {
+        for &day in days {
+            emit(day, f(day));
+        }
+        return;
+    }
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(days.len()) {
+            let (next, f, sender) = (&next, &f, sender.clone());
+            scope.spawn(move || {
+                let mut index = next.fetch_add(1, Ordering::Relaxed);
+                while let Some(&day) = days.get(index) {
+                    if sender.send((index, f(day))).is_err() {
+                        break;
+                    }
+                    index = next.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut emitted = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&emitted) {
+                emit(days[emitted], result);
+                emitted += 1;
+            }
+        }
+    });
+}
+
+/// Runs the days of `options` on `input` with `setup`, using up to
+/// `options.jobs` threads. Text output is printed as days finish, followed by a summary
+/// table if there are several; other formats append to `rows` instead.
+/// Returns whether every implemented day that ran was solved.
+pub fn run(
+    registry: &Registry,
+    options: &cli::Run,
+    setup: &Setup,
+    input: &Input,
+    rows: &mut Vec<Row>,
+) -> bool {
+    let text = options.format == Format::Text;
+    let mut statuses = Vec::with_capacity(options.days.len());
+    in_order(
+        &options.days,
+        options.jobs,
+        |day| solve_day(registry, day, input, options.part, setup),
+        |day, status| {
+            if text {
+                print!("{}", report_day(registry, day, &status));
+            }
+            statuses.push((day, status));
+        },
+    );
+
+    if !text {
+        rows.extend(report::rows(registry, input.user(), &statuses));
+    } else if statuses.len() > 1 {
+        println!();
+        print!("{}", summary(registry, &statuses));
+    }
+    !statuses
+        .iter()
+        .any(|(_, status)| matches!(status, Status::Failed(_)))
+}
+
+fn duration(duration: Duration) -> String {
+    format!("{duration:.1?}")
+}
+
+/// One line naming the time of each phase that ran, and their total.
+fn phases(timings: &Timings) -> String {
+    let mut out = format!("parse {}", duration(timings.parse));
+    if let Some(part1) = timings.part1 {
+        out += &format!(", part1 {}", duration(part1));
+    }
+    if let Some(part2) = timings.part2 {
+        out += &format!(", part2 {}", duration(part2));
+    }
+    out + &format!(", total {}", duration(timings.total()))
+}
+
+/// Table of every day's status and phase timings, with each solved day's
+/// share of the total time.
+pub fn summary(registry: &Registry, statuses: &[(usize, Status)]) -> String {
+    let title = |day: usize| registry.get(day).map_or("", |solution| solution.title());
+    let width = statuses
+        .iter()
+        .map(|&(day, _)| title(day).len())
+        .max()
+        .unwrap_or(0)
+        .max("title".len());
+    let total: Timings = statuses
+        .iter()
+        .filter_map(|(_, status)| match status {
+            Status::Solved(_, timings) => Some(timings),
+            _ => None,
+        })
+        .fold(Timings::default(), |sum, timings| Timings {
+            parse: sum.parse + timings.parse,
+            part1: Some(sum.part1.unwrap_or_default() + timings.part1.unwrap_or_default()),
+            part2: Some(sum.part2.unwrap_or_default() + timings.part2.unwrap_or_default()),
+        });
+    let times = |timings: &Timings| {
+        let share = match total.total().as_secs_f64() {
+            secs if secs > 0.0 => 100.0 * timings.total().as_secs_f64() / secs,
+            _ => 0.0,
+        };
+        let part = |part: Option<Duration>| part.map_or("-".to_string(), duration);
+        format!(
+            "{:>9}  {:>9}  {:>9}  {:>9}  {share:>6.1}%",
+            duration(timings.parse),
+            part(timings.part1),
+            part(timings.part2),
+            duration(timings.total()),
+        )
+    };
+
+    let mut out = format!(
+        "day  {:<width$}  {:>9}  {:>9}  {:>9}  {:>9}  {:>7}  status\n",
+        "title", "parse", "part1", "part2", "total", "share"
+    );
+    for (day, status) in statuses {
+        let columns = match status {
+            Status::Solved(_, timings) => times(timings),
+            _ => format!("{:>9}  {:>9}  {:>9}  {:>9}  {:>7}", "-", "-", "-", "-", "-"),
+        };
+        out += &format!("{day:>3}  {:<width$}  {columns}  {status}\n", title(*day));
+    }
+    out += &format!("     {:<width$}  {}\n", "total", times(&total));
+
+    let count = |f: fn(&Status) -> bool| statuses.iter().filter(|(_, s)| f(s)).count();
+    out += &format!(
+        "{} solved, {} failed, {} not implemented\n",
+        count(|s| matches!(s, Status::Solved(..))),
+        count(|s| matches!(s, Status::Failed(_))),
+        count(|s| matches!(s, Status::NotImplemented)),
+    );
+    out
+}
+
+pub fn list(registry: &Registry) -> String {
+    let width = registry
+        .iter()
+        .map(|solution| solution.title().len())
+        .max()
+        .unwrap_or(0)
+        .max("title".len());
+
+    let mut out = format!("day  {:<width$}  examples\n", "title");
+    for solution in registry.iter() {
+        out += &format!(
+            "{:>3}  {:<width$}  {}\n",
+            solution.day(),
+            solution.title(),
+            solution.examples().len()
+        );
+    }
+    out += &format!(
+        "{} of {} days implemented\n",
+        registry.iter().count(),
+        DAYS.count()
+    );
+    out
+}
+
+/// Each example answer that differs from the puzzle text, as a message.
+fn failed_examples(solution: &dyn Day) -> Vec<String> {
+    let mut failures = Vec::new();
+    for (i, example) in solution.examples().iter().enumerate() {
+        for (part, expected) in [(Part::One, example.part1), (Part::Two, example.part2)] {
+            let Some(expected) = expected else {
+                continue;
+            };
+            let answer = solution.solve(example.input, Some(part));
+            let failure = match answer {
+                Ok(answers) if answers.get(part) == Some(expected) => continue,
+                Ok(answers) => format!(
+                    "expected {expected}, got {}",
+                    answers.get(part).unwrap_or_default()
+                ),
+                Err(err) => err.to_string(),
+            };
+            let n = if part == Part::One { 1 } else { 2 };
+            failures.push(format!("example {} part{n}: {failure}", i + 1));
+        }
+    }
+    failures
+}
+
+/// Runs the examples of the given days. Returns whether they all passed.
+pub fn example(registry: &Registry, days: &[usize]) -> bool {
+    let mut ok = true;
+    let mut ran = 0;
+    for solution in days.iter().filter_map(|&day| registry.get(day)) {
+        if solution.examples().is_empty() {
+            continue;
+        }
+        ran += 1;
+        let failures = failed_examples(solution);
+        if failures.is_empty() {
+            println!("day{:02}: pass", solution.day());
+        } else {
+            println!("day{:02}: fail", solution.day());
+            for failure in failures {
+                println!(">> {failure}");
+            }
+            ok = false;
+        }
+    }
+    if ran == 0 {
+        println!("no examples for the selected days");
+    }
+    ok
+}
+
+/// Outcome of checking one day against its recorded answers.
+pub enum Verdict {
+    Pass,
+    Fail(Vec<String>),
+    Missing(String),
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Pass => write!(f, "pass"),
+            Verdict::Fail(_) => write!(f, "fail"),
+            Verdict::Missing(reason) => write!(f, "missing, {reason}"),
+        }
+    }
+}
+
+fn check_day(solution: &dyn Day, input: &Input, answers: &mut AnswerFile, save: bool) -> Verdict {
+    let day = solution.day();
+    let mut failures = failed_examples(solution);
+    let got = match catch_panic(|| Ok(solution.solve(&input.read(day)?, None)?)) {
+        Ok(got) => got,
+        Err(Error::MissingInput(path)) if failures.is_empty() => {
+            return Verdict::Missing(format!("no input file {path}"));
+        }
+        Err(err) => {
+            failures.push(format!("input: {err}"));
+            return Verdict::Fail(failures);
+        }
+    };
+
+    let mut unknown = Vec::new();
+    for part in [Part::One, Part::Two] {
+        let got = got.get(part).unwrap_or_default();
+        let n = if part == Part::One { 1 } else { 2 };
+        match answers.get(day).and_then(|expected| expected.get(part)) {
+            Some(expected) if expected == got => {}
+            Some(expected) => failures.push(format!("part{n}: expected {expected}, got {got}")),
+            None => unknown.push(format!("part{n}")),
+        }
+    }
+    if !failures.is_empty() {
+        Verdict::Fail(failures)
+    } else if unknown.is_empty() {
+        Verdict::Pass
+    } else if save && answers.fill(day, &got) {
+        Verdict::Missing(format!("recorded {}", unknown.join(" and ")))
+    } else {
+        Verdict::Missing(format!("no recorded {}", unknown.join(" and ")))
+    }
+}
+
+/// Runs the examples of the given days, solves their inputs and compares
+/// the answers with the input's answers file, recording unknown ones if
+/// `save` is set. Returns whether no implemented day failed.
+pub fn check(
+    registry: &Registry,
+    days: &[usize],
+    input: &Input,
+    save: bool,
+) -> Result<bool, Error> {
+    let path = input.answers_path();
+    let mut answers = match &path {
+        Some(path) => AnswerFile::load(path)?,
+        None => AnswerFile::default(),
+    };
+    let before = answers.clone();
+
+    let mut counts = [0; 4];
+    for &day in days {
+        let Some(solution) = registry.get(day) else {
+            println!("day{day:02}: not implemented");
+            counts[3] += 1;
+            continue;
+        };
+        let verdict = check_day(solution, input, &mut answers, save);
+        println!("day{:02}: {verdict}", solution.day());
+        let index = match &verdict {
+            Verdict::Pass => 0,
+            Verdict::Fail(failures) => {
+                for failure in failures {
+                    println!(">> {failure}");
+                }
+                1
+            }
+            Verdict::Missing(_) => 2,
+        };
+        counts[index] += 1;
+    }
+    println!(
+        "{} passed, {} failed, {} missing, {} not implemented",
+        counts[0], counts[1], counts[2], counts[3]
+    );
+
+    if let Some(path) = path.filter(|_| answers != before) {
+        answers.save(path)?;
+    }
+    Ok(counts[1] == 0)
+}
+
+/// Benchmarks each day of `input` and prints the statistics of every phase,
+/// comparing them with and saving them to baselines as requested. Returns
+/// whether every implemented day could be timed without regressions.
+pub fn bench(registry: &Registry, options: &cli::Bench, input: &Input) -> Result<bool, Error> {
+    let baseline = match &options.baseline {
+        Some(path) => Some(Baseline::load(path)?),
+        None => None,
+    };
+    let mut saved = match &options.save_baseline {
+        Some(path) => Some(Baseline::load(path)?),
+        None => None,
+    };
+
+    let mut ok = true;
+    for solution in options.days.iter().filter_map(|&day| registry.get(day)) {
+        let day = solution.day();
+        let phases = input.read(day).and_then(|source| {
+            Ok(bench::measure(
+                solution,
+                &source,
+                options.warmup,
+                options.iterations,
+            )?)
+        });
+        let phases = match phases {
+            Ok(phases) => phases,
+            Err(err) => {
+                println!("day{day:02}: {err}");
+                ok = false;
+                continue;
+            }
+        };
+
+        println!(
+            "day{day:02}: {}, {} runs after {} warm-up",
+            solution.title(),
+            options.iterations,
+            options.warmup
+        );
+        println!(
+            "  {:<5}  {:>9}  {:>9}  {:>9}  {:>9}",
+            "", "mean", "median", "std dev", "min"
+        );
+        for (phase, stats) in bench::PHASES.iter().zip(&phases) {
+            println!(
+                "  {phase:<5}  {:>9}  {:>9}  {:>9}  {:>9}",
+                duration(stats.mean),
+                duration(stats.median),
+                duration(stats.std_dev),
+                duration(stats.min)
+            );
+        }
+
+        if let Some(previous) = baseline.as_ref().and_then(|baseline| baseline.get(day)) {
+            for regression in bench::compare(previous, &phases, options.threshold) {
+                println!(">> regression in {regression}");
+                ok = false;
+            }
+        }
+        if let Some(saved) = &mut saved {
+            saved.insert(day, phases);
+        }
+    }
+
+    if let (Some(path), Some(saved)) = (&options.save_baseline, saved) {
+        saved.save(path)?;
+    }
+    Ok(ok)
+}
+
+pub fn disasm(source: &str, optimize: bool, view: View) -> Result<String, Error> {
+    let mut tape = Tape::from_str(source).map_err(ParseError::from)?;
+    if optimize {
+        tape = optimize::optimize(&tape);
+    }
+    Ok(match view {
+        View::Listing => disasm::disassemble(&tape)
+            .iter()
+            .map(|line| format!("{line}\n"))
+            .collect(),
+        View::Decompile => decompile::decompile(&tape),
+        View::Lint => lint::lint(&tape)
+            .iter()
+            .map(|finding| format!("{finding}\n"))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_order() {
+        let days = [3, 1, 2, 1, 5];
+        let mut emitted = Vec::new();
+        in_order(
+            &days,
+            3,
+            |day| {
+                // Later days finish first.
+                thread::sleep(Duration::from_millis(10 * (5 - day as u64)));
+                day * 10
+            },
+            |day, result| emitted.push((day, result)),
+        );
+        assert_eq!(emitted, [(3, 30), (1, 10), (2, 20), (1, 10), (5, 50)]);
+    }
+
+    #[test]
+    fn test_panics_are_reported() {
+        use advent_of_code_2019::solution::{Entry, Solution, SolveError};
+
+        struct Broken;
+
+        impl Solution for Broken {
+            const DAY: usize = 1;
+            const TITLE: &'static str = "Broken";
+
+            type Input = ();
+            type Part1 = usize;
+            type Part2 = usize;
+
+            fn parse(_input: &str) -> Result<(), ParseError> {
+                Ok(())
+            }
+
+            fn part1(_input: &()) -> Result<usize, SolveError> {
+                panic!("out of fuel")
+            }
+
+            fn part2(_input: &()) -> Result<usize, SolveError> {
+                Ok(2)
+            }
+        }
+
+        const BROKEN: &dyn Day = &Entry::<Broken>::new();
+        let mut registry = Registry::default();
+        registry.register(BROKEN);
+        let input = Input::File("Cargo.toml".into());
+
+        let mut reports = Vec::new();
+        in_order(
+            &[1, 1],
+            2,
+            |day| solve_day(&registry, day, &input, None, &Setup::default()),
+            |_, status| reports.push(status.to_string()),
+        );
+        assert_eq!(reports, ["failed: panicked: out of fuel"; 2]);
+
+        let status = solve_day(&registry, 1, &input, Some(Part::Two), &Setup::default());
+        assert!(matches!(status, Status::Solved(..)));
+    }
+
+    #[test]
+    fn test_summary() {
+        let ms = Duration::from_millis;
+        let statuses = [
+            (
+                1,
+                Status::Solved(
+                    Answers::default(),
+                    Timings {
+                        parse: ms(1),
+                        part1: Some(ms(2)),
+                        part2: Some(ms(5)),
+                    },
+                ),
+            ),
+            (
+                2,
+                Status::Failed(Error::MissingInput("input/day02.txt".into())),
+            ),
+            (
+                3,
+                Status::Solved(
+                    Answers::default(),
+                    Timings {
+                        parse: ms(2),
+                        part1: None,
+                        part2: Some(ms(10)),
+                    },
+                ),
+            ),
+            (15, Status::NotImplemented),
+        ];
+        assert_eq!(
+            summary(&Registry::default(), &statuses),
+            concat!(
+                "day  title      parse      part1      part2      total    share  status\n",
+                "  1             1.0ms      2.0ms      5.0ms      8.0ms    40.0%  solved\n",
+                "  2                 -          -          -          -        -  \
+                 failed: missing input file input/day02.txt\n",
+                "  3             2.0ms          -     10.0ms     12.0ms    60.0%  solved\n",
+                " 15                 -          -          -          -        -  not implemented\n",
+                "     total      3.0ms      2.0ms     15.0ms     20.0ms   100.0%\n",
+                "2 solved, 1 failed, 1 not implemented\n",
+            )
+        );
+    }
+}
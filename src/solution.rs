@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::num::{NonZeroUsize, ParseIntError};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::intcode::patch::{Patch, PatchError};
+use crate::intcode::transcript::Recorder;
+use crate::intcode::{RunStatus, Tape};
+
+/// Days of the calendar.
+pub const DAYS: std::ops::RangeInclusive<usize> = 1..=25;
+
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Caps the threads a single solution may use, so that days solved side by
+/// side do not oversubscribe the machine.
+pub fn set_threads(threads: usize) {
+    THREADS.store(threads.max(1), Ordering::Relaxed);
+}
+
+/// Threads a solution may use: the cap set by `set_threads`, or the
+/// available parallelism if there is none.
+pub fn threads() -> usize {
+    match THREADS.load(Ordering::Relaxed) {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        threads => threads,
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("{0}")]
+pub struct ParseError(pub String);
+
+impl ParseError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl From<ParseIntError> for ParseError {
+    fn from(value: ParseIntError) -> Self {
+        Self(value.to_string())
+    }
+}
+
+/// Why a day could not be answered: its input did not parse, or a part
+/// failed on it.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SolveError {
+    #[error("parse error: {0}")]
+    Parse(#[from] ParseError),
+    #[error("patch error: {0}")]
+    Patch(#[from] PatchError),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl SolveError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self::Failed(message.into())
+    }
+}
+
+/// Fails unless an Intcode program ran until it halted.
+pub fn halted(status: RunStatus) -> Result<(), SolveError> {
+    match status {
+        RunStatus::Halt { .. } => Ok(()),
+        RunStatus::Poll { pc, .. } => Err(SolveError::new(format!(
+            "program waits for input at pc {pc}"
+        ))),
+        RunStatus::Breakpoint { pc, .. } | RunStatus::StepLimit { pc, .. } => {
+            Err(SolveError::new(format!("program stopped at pc {pc}")))
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Part {
+    One,
+    Two,
+}
+
+impl FromStr for Part {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            _ => Err(ParseError::new(format!(
+                "invalid part `{s}`: expected 1 or 2"
+            ))),
+        }
+    }
+}
+
+/// Example input from the puzzle text, with the answers it gives for the
+/// parts it applies to.
+#[derive(Debug)]
+pub struct Example {
+    pub input: &'static str,
+    pub part1: Option<&'static str>,
+    pub part2: Option<&'static str>,
+}
+
+/// Changes from the command line to the Intcode program of a day.
+#[derive(Clone, Debug, Default)]
+pub struct Setup {
+    pub patch: Option<Patch>,
+    /// Records the I/O of the program. Days without one are not recorded.
+    pub recorder: Option<Recorder>,
+}
+
+/// One day of the calendar: how to read its puzzle input and how to answer
+/// both parts from it.
+pub trait Solution {
+    const DAY: usize;
+    const TITLE: &'static str;
+    const EXAMPLES: &'static [Example] = &[];
+
+    type Input;
+    type Part1: fmt::Display;
+    type Part2: fmt::Display;
+
+    fn parse(input: &str) -> Result<Self::Input, ParseError>;
+
+    /// The Intcode program the parts run, for days that run one, so that a
+    /// `Setup` can be applied to it.
+    fn tape(_input: &mut Self::Input) -> Option<&mut Tape> {
+        None
+    }
+
+    fn part1(input: &Self::Input) -> Result<Self::Part1, SolveError>;
+    fn part2(input: &Self::Input) -> Result<Self::Part2, SolveError>;
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Answers {
+    pub part1: Option<String>,
+    pub part2: Option<String>,
+}
+
+impl Answers {
+    pub fn get(&self, part: Part) -> Option<&str> {
+        match part {
+            Part::One => self.part1.as_deref(),
+            Part::Two => self.part2.as_deref(),
+        }
+    }
+}
+
+/// Wall-clock time spent in each phase of solving a day.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Timings {
+    pub parse: Duration,
+    pub part1: Option<Duration>,
+    pub part2: Option<Duration>,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.parse + self.part1.unwrap_or_default() + self.part2.unwrap_or_default()
+    }
+}
+
+fn timed<T: fmt::Display>(
+    f: impl FnOnce() -> Result<T, SolveError>,
+) -> Result<(String, Duration), SolveError> {
+    let start = Instant::now();
+    let answer = f()?;
+    let elapsed = start.elapsed();
+    Ok((answer.to_string(), elapsed))
+}
+
+/// Type-erased `Solution`, so days with different input and answer types
+/// can live in one registry.
+pub trait Day: Sync {
+    fn day(&self) -> usize;
+    fn title(&self) -> &'static str;
+    fn examples(&self) -> &'static [Example];
+
+    /// Answers `part`, or both parts if it is `None`, with `setup` applied to
+    /// the day's Intcode program, timing each phase.
+    fn solve_timed(
+        &self,
+        input: &str,
+        part: Option<Part>,
+        setup: &Setup,
+    ) -> Result<(Answers, Timings), SolveError>;
+
+    fn solve(&self, input: &str, part: Option<Part>) -> Result<Answers, SolveError> {
+        Ok(self.solve_timed(input, part, &Setup::default())?.0)
+    }
+}
+
+/// The `Day` of a `Solution`. Each day module exposes one as its
+/// `SOLUTION`, which `registry` collects.
+pub struct Entry<S>(PhantomData<fn() -> S>);
+
+impl<S> Entry<S> {
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<S> Default for Entry<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Solution> Day for Entry<S> {
+    fn day(&self) -> usize {
+        S::DAY
+    }
+
+    fn title(&self) -> &'static str {
+        S::TITLE
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        S::EXAMPLES
+    }
+
+    fn solve_timed(
+        &self,
+        input: &str,
+        part: Option<Part>,
+        setup: &Setup,
+    ) -> Result<(Answers, Timings), SolveError> {
+        let start = Instant::now();
+        let mut input = S::parse(input)?;
+        if let Some(patch) = &setup.patch {
+            let tape = S::tape(&mut input).ok_or(PatchError::NotIntcode)?;
+            patch.apply(tape)?;
+        }
+        if let (Some(recorder), Some(tape)) = (&setup.recorder, S::tape(&mut input)) {
+            tape.record(recorder.clone());
+        }
+        let parse = start.elapsed();
+
+        let part1 = (part != Some(Part::Two))
+            .then(|| timed(|| S::part1(&input)))
+            .transpose()?;
+        let part2 = (part != Some(Part::One))
+            .then(|| timed(|| S::part2(&input)))
+            .transpose()?;
+        let timings = Timings {
+            parse,
+            part1: part1.as_ref().map(|(_, elapsed)| *elapsed),
+            part2: part2.as_ref().map(|(_, elapsed)| *elapsed),
+        };
+        let answers = Answers {
+            part1: part1.map(|(answer, _)| answer),
+            part2: part2.map(|(answer, _)| answer),
+        };
+        Ok((answers, timings))
+    }
+}
+
+/// Every implemented day, ordered by day number.
+#[derive(Default)]
+pub struct Registry {
+    days: BTreeMap<usize, &'static dyn Day>,
+}
+
+impl Registry {
+    pub fn register(&mut self, solution: &'static dyn Day) -> &mut Self {
+        let previous = self.days.insert(solution.day(), solution);
+        assert!(
+            previous.is_none(),
+            "day {} registered twice",
+            solution.day()
+        );
+        self
+    }
+
+    pub fn get(&self, day: usize) -> Option<&dyn Day> {
+        self.days.get(&day).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Day> {
+        self.days.values().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sum;
+
+    impl Solution for Sum {
+        const DAY: usize = 26;
+        const TITLE: &'static str = "Sum";
+
+        type Input = Vec<usize>;
+        type Part1 = usize;
+        type Part2 = String;
+
+        fn parse(input: &str) -> Result<Vec<usize>, ParseError> {
+            Ok(input.lines().map(str::parse).collect::<Result<_, _>>()?)
+        }
+
+        fn part1(input: &Vec<usize>) -> Result<usize, SolveError> {
+            Ok(input.iter().sum())
+        }
+
+        fn part2(input: &Vec<usize>) -> Result<String, SolveError> {
+            match input.len() {
+                0 => Err(SolveError::new("no values")),
+                n => Ok(format!("{n} values")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        const SUM: &dyn Day = &Entry::<Sum>::new();
+        let mut registry = Registry::default();
+        registry.register(SUM);
+
+        let day = registry.get(26).unwrap();
+        assert_eq!(day.title(), "Sum");
+        assert_eq!(
+            day.solve("1\n2\n3\n", None),
+            Ok(Answers {
+                part1: Some("6".to_string()),
+                part2: Some("3 values".to_string()),
+            })
+        );
+        assert_eq!(
+            day.solve("1\n2\n3\n", Some(Part::Two)),
+            Ok(Answers {
+                part1: None,
+                part2: Some("3 values".to_string()),
+            })
+        );
+        assert!(matches!(
+            day.solve("1\nx\n", None),
+            Err(SolveError::Parse(_))
+        ));
+        assert_eq!(
+            day.solve("", Some(Part::Two)),
+            Err(SolveError::new("no values"))
+        );
+        let setup = Setup {
+            patch: Some("0=1".parse().unwrap()),
+            ..Setup::default()
+        };
+        assert_eq!(
+            day.solve_timed("1\n", None, &setup),
+            Err(SolveError::Patch(PatchError::NotIntcode))
+        );
+        assert!(registry.get(1).is_none());
+    }
+}